@@ -0,0 +1,205 @@
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use crate::error::MergeError;
+use crate::merge::{default_registry_sources_path, package_root, select_bin_target, CargoData, Merge};
+use crate::opts::{Opts, TargetProfile};
+use crate::source_map::{LineOrigin, MergedSource};
+
+/// The binary target merged at the root of the merged file
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum Target {
+    /// The only binary of the package, or else the one cargo runs by default. A package without binary
+    /// is merged as its library alone.
+    #[default]
+    Default,
+    /// A binary target, by name
+    Bin(String),
+    /// An example target, by name
+    Example(String),
+}
+
+/// Merges the sources of a package into a single file, without writing anything nor changing the current directory.
+///
+/// ```
+/// use cargo_merge::{MergeBuilder, Target};
+///
+/// let output = MergeBuilder::new("tests_data/lib_and_bin")
+///     .target(Target::Bin("lib_and_bin".to_string()))
+///     .tree_shake(true)
+///     .merge()
+///     .unwrap();
+/// assert_eq!(output.package_name, "lib_and_bin");
+/// assert!(output.source.contains("fn main()"));
+/// ```
+#[derive(Debug)]
+pub struct MergeBuilder {
+    package_path: PathBuf,
+    target: Target,
+    opts: Opts,
+}
+
+/// A merged file, with what it was built from
+#[derive(Debug, Clone)]
+pub struct MergeOutput {
+    /// Source code of the merged file
+    pub source: String,
+    /// Name of the package, as used in the source code
+    pub package_name: String,
+    /// Name of the binary (or example) target merged at the root, if any
+    pub target_name: Option<String>,
+    /// Rust edition of the package
+    pub edition: String,
+    /// Features enabled for the package
+    pub features: BTreeSet<String>,
+    /// Names of the modules the dependency crates, then the lib crate of the package, are merged into
+    pub library_modules: Vec<String>,
+    /// Source files read by the merge, manifest of the package included
+    pub files: Vec<PathBuf>,
+    origins: Vec<Option<LineOrigin>>,
+}
+
+impl MergeOutput {
+    /// Where a line of the merged file, given by its number starting at 1, comes from.
+    /// Lines generated by the merge have no origin.
+    pub fn origin(&self, line: usize) -> Option<&LineOrigin> {
+        line.checked_sub(1).and_then(|index| self.origins.get(index)).and_then(Option::as_ref)
+    }
+}
+
+impl MergeBuilder {
+    /// Merge the package found at the given path: its folder, or its `Cargo.toml` file.
+    /// A relative path is resolved against the current directory.
+    pub fn new(package_path: impl Into<PathBuf>) -> MergeBuilder {
+        MergeBuilder {
+            package_path: package_path.into(),
+            target: Target::Default,
+            opts: Opts::default(),
+        }
+    }
+
+    /// Select the binary target merged at the root of the file
+    pub fn target(mut self, target: Target) -> MergeBuilder {
+        self.target = target;
+        self
+    }
+
+    /// Enable features of the package, against which `#[cfg(feature = "...")]` attributes are evaluated
+    pub fn features<I: IntoIterator<Item = S>, S: Into<String>>(mut self, features: I) -> MergeBuilder {
+        self.opts.features.extend(features.into_iter().map(Into::into));
+        self
+    }
+
+    /// Do not enable the `default` feature of the package
+    pub fn no_default_features(mut self, no_default_features: bool) -> MergeBuilder {
+        self.opts.no_default_features = no_default_features;
+        self
+    }
+
+    /// Set a configuration option of the judge, as `name` or `name="value"`
+    pub fn cfg(mut self, option: impl Into<String>) -> MergeBuilder {
+        self.opts.cfg.push(option.into());
+        self
+    }
+
+    /// Platform of the judge, against which the target `cfg` predicates are evaluated
+    pub fn target_profile(mut self, target_profile: TargetProfile) -> MergeBuilder {
+        self.opts.target_profile = Some(target_profile);
+        self
+    }
+
+    /// Merge the dependencies coming from a registry, from their sources in the given folder
+    /// (relative to the package folder), or in the cargo registry cache when `None`
+    pub fn registry_dependencies(mut self, registry_sources: Option<PathBuf>) -> MergeBuilder {
+        self.opts.merge_registry_dependencies = true;
+        self.opts.registry_sources = registry_sources;
+        self
+    }
+
+    /// Remove all the usages of the `eprint!` and `eprintln!` macros
+    pub fn silence_standard_error_output(mut self, silence: bool) -> MergeBuilder {
+        self.opts.remove_error_output = silence;
+        self
+    }
+
+    /// Keep the `#[cfg(test)]` items and the `#[test]` functions, that are removed by default
+    pub fn keep_tests(mut self, keep_tests: bool) -> MergeBuilder {
+        self.opts.keep_tests = keep_tests;
+        self
+    }
+
+    /// Remove the doc comments and `#[doc = "..."]` attributes
+    pub fn strip_doc_comments(mut self, strip_doc_comments: bool) -> MergeBuilder {
+        self.opts.remove_doc_comments = strip_doc_comments;
+        self
+    }
+
+    /// Remove the items that can't be reached from the `main` function
+    pub fn tree_shake(mut self, tree_shake: bool) -> MergeBuilder {
+        self.opts.tree_shake = tree_shake;
+        self
+    }
+
+    /// Remove comments, doc comments and whitespace from the merged file
+    pub fn minify(mut self, minify: bool) -> MergeBuilder {
+        self.opts.minify = minify;
+        self
+    }
+
+    /// Merge the package with the selected target
+    pub fn merge(self) -> Result<MergeOutput, MergeError> {
+        let (package_root_path, merge) = self.into_merge()?;
+        let cargo_data = merge.load_package(&package_root_path, registry_sources_path(&merge.opts, &package_root_path)?.as_deref())?;
+        let library_output = merge.merge_library(&cargo_data)?;
+        let bin_target = select_bin_target(&cargo_data, &merge.opts)?;
+        let output = merge.merge_bin(&cargo_data, &library_output, bin_target)?;
+        Ok(merge_output(output, &cargo_data, bin_target.map(|bin_target| bin_target.name.clone()), merge.visited_files()))
+    }
+
+    /// Merge every binary target of the package, each one into its own file sharing the library part.
+    /// The selected target is ignored.
+    pub fn merge_all_bins(self) -> Result<Vec<MergeOutput>, MergeError> {
+        let (package_root_path, merge) = self.into_merge()?;
+        let cargo_data = merge.load_package(&package_root_path, registry_sources_path(&merge.opts, &package_root_path)?.as_deref())?;
+        let library_output = merge.merge_library(&cargo_data)?;
+        cargo_data.bin_targets.iter()
+            .map(|bin_target| {
+                let output = merge.merge_bin(&cargo_data, &library_output, Some(bin_target))?;
+                Ok(merge_output(output, &cargo_data, Some(bin_target.name.clone()), merge.visited_files()))
+            })
+            .collect()
+    }
+
+    /// The folder of the package, and the merge configured by the options
+    fn into_merge(mut self) -> Result<(PathBuf, Merge), MergeError> {
+        let package_root_path = package_root(&self.package_path)?;
+        match self.target {
+            Target::Default => {}
+            Target::Bin(name) => self.opts.bin = Some(name),
+            Target::Example(name) => self.opts.example = Some(name),
+        }
+        Ok((package_root_path, Merge::new(self.opts)))
+    }
+}
+
+fn registry_sources_path(opts: &Opts, package_root_path: &Path) -> Result<Option<PathBuf>, MergeError> {
+    if !opts.merge_registry_dependencies {
+        return Ok(None);
+    }
+    match &opts.registry_sources {
+        Some(registry_sources) => Ok(Some(package_root_path.join(registry_sources))),
+        None => default_registry_sources_path().map(Some),
+    }
+}
+
+fn merge_output(output: MergedSource, cargo_data: &CargoData, target_name: Option<String>, files: Vec<PathBuf>) -> MergeOutput {
+    MergeOutput {
+        source: output.text,
+        package_name: cargo_data.package_name.clone(),
+        target_name,
+        edition: cargo_data.edition.clone(),
+        features: cargo_data.features.clone(),
+        library_modules: cargo_data.library_module_names(),
+        files,
+        origins: output.origins,
+    }
+}
@@ -0,0 +1,208 @@
+use std::collections::BTreeSet;
+use crate::opts::{Opts, TargetProfile};
+use cargo_platform::Platform;
+use proc_macro2::TokenStream;
+use quote::{quote, ToTokens};
+use syn::{Expr, ExprLit, Lit, Meta, MetaNameValue, Token};
+use syn::parse::Parser;
+use syn::punctuated::Punctuated;
+
+/// Configuration options shared by all the crates: the ones of the target profile and the ones given with `--cfg`
+#[derive(Default)]
+pub(crate) struct CfgOptions {
+    /// Options set, as `name` or `name = "value"`
+    options: BTreeSet<(String, Option<String>)>,
+    /// Names of the options that are fully known, and are not set if they are not in `options`
+    known_names: BTreeSet<String>,
+    /// Target triple of the target profile, if any
+    target_triple: Option<&'static str>,
+}
+
+impl CfgOptions {
+    pub(crate) fn new(opts: &Opts) -> CfgOptions {
+        let mut cfg_options = CfgOptions::default();
+        if let Some(target_profile) = opts.target_profile {
+            for (name, value) in target_profile_options(target_profile) {
+                cfg_options.options.insert((name.to_string(), value.map(String::from)));
+            }
+            cfg_options.known_names.extend(TARGET_OPTION_NAMES.iter().map(|name| name.to_string()));
+            cfg_options.target_triple = Some(target_triple(target_profile));
+        }
+        // Given like rustc options: `name` or `name="value"`
+        for cfg in &opts.cfg {
+            let option = match cfg.split_once('=') {
+                Some((name, value)) => (name.trim().to_string(), Some(value.trim().trim_matches('"').to_string())),
+                None => (cfg.trim().to_string(), None),
+            };
+            cfg_options.options.insert(option);
+        }
+        cfg_options
+    }
+
+    /// Whether a dependency declared for a platform (`[target.'cfg(...)'.dependencies]`) is used on the judge:
+    /// it is not only when its platform is known not to match
+    pub(crate) fn matches_platform(&self, platform: &Platform) -> bool {
+        match platform {
+            Platform::Name(name) => self.target_triple.is_none_or(|target_triple| target_triple == name),
+            Platform::Cfg(predicate) => {
+                let features = BTreeSet::new();
+                let evaluator = CfgEvaluator { features: &features, keep_tests: true, cfg_options: self };
+                match predicate.to_string().parse() {
+                    Ok(tokens) => !matches!(evaluator.evaluate_tokens(tokens), Cfg::False),
+                    Err(_) => true,
+                }
+            }
+        }
+    }
+
+    /// Whether an option is set, or `None` if it is left for the compiler
+    fn is_set(&self, name: String, value: Option<String>) -> Option<bool> {
+        if self.known_names.contains(&name) || self.options.contains(&(name.clone(), value.clone())) {
+            Some(self.options.contains(&(name, value)))
+        } else {
+            None
+        }
+    }
+}
+
+/// Names of the configuration options decided by a target profile
+const TARGET_OPTION_NAMES: [&str; 13] = [
+    "target_os", "target_family", "unix", "windows", "target_arch", "target_pointer_width", "target_endian",
+    "target_env", "target_vendor", "target_has_atomic", "panic", "debug_assertions", "overflow_checks",
+];
+
+/// Target triple of a target profile
+fn target_triple(target_profile: TargetProfile) -> &'static str {
+    match target_profile {
+        TargetProfile::Linux => "x86_64-unknown-linux-gnu",
+        TargetProfile::Windows => "x86_64-pc-windows-msvc",
+        TargetProfile::Macos => "x86_64-apple-darwin",
+    }
+}
+
+/// Configuration options of a target profile: a 64 bits x86 platform, compiling in release mode
+fn target_profile_options(target_profile: TargetProfile) -> Vec<(&'static str, Option<&'static str>)> {
+    let mut options = vec![
+        ("target_arch", Some("x86_64")),
+        ("target_pointer_width", Some("64")),
+        ("target_endian", Some("little")),
+        ("target_has_atomic", Some("8")),
+        ("target_has_atomic", Some("16")),
+        ("target_has_atomic", Some("32")),
+        ("target_has_atomic", Some("64")),
+        ("target_has_atomic", Some("ptr")),
+        ("panic", Some("unwind")),
+    ];
+    match target_profile {
+        TargetProfile::Linux => options.extend([
+            ("target_os", Some("linux")),
+            ("target_family", Some("unix")),
+            ("unix", None),
+            ("target_env", Some("gnu")),
+            ("target_vendor", Some("unknown")),
+        ]),
+        TargetProfile::Windows => options.extend([
+            ("target_os", Some("windows")),
+            ("target_family", Some("windows")),
+            ("windows", None),
+            ("target_env", Some("msvc")),
+            ("target_vendor", Some("pc")),
+        ]),
+        TargetProfile::Macos => options.extend([
+            ("target_os", Some("macos")),
+            ("target_family", Some("unix")),
+            ("unix", None),
+            ("target_env", Some("")),
+            ("target_vendor", Some("apple")),
+        ]),
+    }
+    options
+}
+
+/// Evaluates `cfg` predicates with the configuration known at merge time. The configuration options
+/// that are not known are left for the compiler to evaluate.
+pub(crate) struct CfgEvaluator<'a> {
+    /// Features enabled for the crate being merged
+    pub(crate) features: &'a BTreeSet<String>,
+    /// Whether the test items are kept, in which case `test` is left for the compiler
+    pub(crate) keep_tests: bool,
+    /// Options of the target profile and given with `--cfg`
+    pub(crate) cfg_options: &'a CfgOptions,
+}
+
+/// A `cfg` predicate, simplified with the known configuration options
+pub(crate) enum Cfg {
+    True,
+    False,
+    /// The predicate depends on configuration options only known by the compiler
+    Unknown(TokenStream),
+}
+
+impl Cfg {
+    fn from_option(is_set: Option<bool>, predicate: &Meta) -> Cfg {
+        match is_set {
+            Some(true) => Cfg::True,
+            Some(false) => Cfg::False,
+            None => Cfg::Unknown(predicate.to_token_stream()),
+        }
+    }
+}
+
+impl CfgEvaluator<'_> {
+    /// Evaluate a predicate, given as the tokens of a `cfg(...)` attribute or `cfg!(...)` macro
+    pub(crate) fn evaluate_tokens(&self, tokens: TokenStream) -> Cfg {
+        match syn::parse2::<Meta>(tokens.clone()) {
+            Ok(predicate) => self.evaluate(&predicate),
+            Err(_) => Cfg::Unknown(tokens),
+        }
+    }
+
+    pub(crate) fn evaluate(&self, predicate: &Meta) -> Cfg {
+        match predicate {
+            Meta::Path(path) if path.is_ident("test") && !self.keep_tests => Cfg::False,
+            Meta::NameValue(MetaNameValue { path, value: Expr::Lit(ExprLit { lit: Lit::Str(value), .. }), .. }) if path.is_ident("feature") => {
+                if self.features.contains(&value.value()) { Cfg::True } else { Cfg::False }
+            }
+            Meta::Path(path) if path.get_ident().is_some() && !path.is_ident("test") => {
+                let name = path.get_ident().unwrap().to_string();
+                Cfg::from_option(self.cfg_options.is_set(name, None), predicate)
+            }
+            Meta::NameValue(MetaNameValue { path, value: Expr::Lit(ExprLit { lit: Lit::Str(value), .. }), .. }) if path.get_ident().is_some() => {
+                let name = path.get_ident().unwrap().to_string();
+                Cfg::from_option(self.cfg_options.is_set(name, Some(value.value())), predicate)
+            }
+            Meta::List(list) if list.path.is_ident("not") => match self.evaluate_tokens(list.tokens.clone()) {
+                Cfg::True => Cfg::False,
+                Cfg::False => Cfg::True,
+                Cfg::Unknown(tokens) => Cfg::Unknown(quote!(not(#tokens))),
+            },
+            Meta::List(list) if list.path.is_ident("all") || list.path.is_ident("any") => {
+                let predicates = match Punctuated::<Meta, Token![,]>::parse_terminated.parse2(list.tokens.clone()) {
+                    Ok(predicates) => predicates,
+                    Err(_) => return Cfg::Unknown(predicate.to_token_stream()),
+                };
+                // `all` is decided by a false predicate, `any` by a true one
+                let is_all = list.path.is_ident("all");
+                let mut unknown_predicates = Vec::new();
+                for predicate in &predicates {
+                    match self.evaluate(predicate) {
+                        Cfg::True if !is_all => return Cfg::True,
+                        Cfg::False if is_all => return Cfg::False,
+                        Cfg::Unknown(tokens) => unknown_predicates.push(tokens),
+                        _ => {}
+                    }
+                }
+                match unknown_predicates.as_slice() {
+                    [] if is_all => Cfg::True,
+                    [] => Cfg::False,
+                    [unknown_predicate] => Cfg::Unknown(unknown_predicate.clone()),
+                    _ => {
+                        let operator = &list.path;
+                        Cfg::Unknown(quote!(#operator(#(#unknown_predicates),*)))
+                    }
+                }
+            }
+            _ => Cfg::Unknown(predicate.to_token_stream()),
+        }
+    }
+}
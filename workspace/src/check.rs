@@ -0,0 +1,89 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use log::{debug, warn};
+use serde_derive::Deserialize;
+use crate::error::{CompileError, MergeError};
+use crate::source_map::MergedSource;
+
+/// A diagnostic emitted by rustc with `--error-format=json`
+#[derive(Deserialize)]
+struct Diagnostic {
+    message: String,
+    level: String,
+    spans: Vec<DiagnosticSpan>,
+}
+
+#[derive(Deserialize)]
+struct DiagnosticSpan {
+    line_start: usize,
+    column_start: usize,
+    is_primary: bool,
+}
+
+/// The rustc program, overridden by the `RUSTC` environment variable like cargo does
+pub(crate) fn rustc() -> String {
+    env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string())
+}
+
+/// Create a new temporary directory for the compiler outputs, unique even when several merges run in the same process
+pub(crate) fn temporary_dir(purpose: &str) -> Result<PathBuf, MergeError> {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let counter = COUNTER.fetch_add(1, Ordering::Relaxed);
+    let dir = env::temp_dir().join(format!("cargo-merge-{}-{}-{}", purpose, std::process::id(), counter));
+    fs::create_dir_all(&dir).map_err(MergeError::io(&dir))?;
+    Ok(dir)
+}
+
+pub(crate) fn remove_temporary_dir(dir: &Path) {
+    if let Err(error) = fs::remove_dir_all(dir) {
+        debug!("Unable to remove the temporary directory {:?}: {}", dir, error);
+    }
+}
+
+/// Compile the merged file with rustc as a crate of the given type (`bin` or `lib`), returning the errors found, located in the original sources
+pub(crate) fn check(merged_file_path: &Path, merged_source: &MergedSource, edition: &str, crate_type: &str) -> Result<Vec<CompileError>, MergeError> {
+    // Only the metadata is emitted, in a temporary directory, to keep the output directory clean
+    let out_dir = temporary_dir("check")?;
+    let rustc = rustc();
+    let output = Command::new(&rustc)
+        .args(["--edition", edition, "--crate-type", crate_type, "--crate-name", "merged", "--emit=metadata", "--error-format=json"])
+        .arg("--out-dir")
+        .arg(&out_dir)
+        .arg(merged_file_path)
+        .output()
+        .map_err(MergeError::io(&rustc))?;
+    remove_temporary_dir(&out_dir);
+
+    let mut errors = Vec::new();
+    for line in String::from_utf8_lossy(&output.stderr).lines() {
+        let diagnostic: Diagnostic = match serde_json::from_str(line) {
+            Ok(diagnostic) => diagnostic,
+            Err(_) => {
+                warn!("Unexpected rustc output: {}", line);
+                continue;
+            }
+        };
+        if diagnostic.level != "error" || diagnostic.message.starts_with("aborting due to") {
+            continue;
+        }
+        let span = diagnostic.spans.iter().find(|span| span.is_primary).or_else(|| diagnostic.spans.first());
+        let location = match span {
+            Some(span) => match merged_source.origin(span.line_start) {
+                Some(origin) => format!("{}:{} (crate {}, merged at line {})", origin.file_path.display(), origin.line, origin.crate_name, span.line_start),
+                None => format!("{}:{}:{}", merged_file_path.display(), span.line_start, span.column_start),
+            },
+            None => merged_file_path.display().to_string(),
+        };
+        errors.push(CompileError { message: diagnostic.message, location });
+    }
+    if errors.is_empty() && !output.status.success() {
+        errors.push(CompileError {
+            message: "rustc failed without reporting an error".to_string(),
+            location: merged_file_path.display().to_string(),
+        });
+    }
+    Ok(errors)
+}
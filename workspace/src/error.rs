@@ -0,0 +1,117 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use crate::opts::SizeLimit;
+
+/// An error that stopped a merge
+#[derive(Debug)]
+pub enum MergeError {
+    /// No `Cargo.toml` file was found in the folder the merge was started from, nor in its parents
+    PackageRootNotFound { search_start: PathBuf },
+    /// A manifest or lock file could not be read or understood
+    ManifestParse { path: PathBuf, message: String },
+    /// A source map written alongside a merged file could not be understood
+    SourceMap { path: PathBuf, message: String },
+    /// A module declared with `mod name;` has no file
+    ModuleNotFound {
+        module_name: String,
+        /// File declaring the module, and line of the declaration
+        declaring_file: PathBuf,
+        line: usize,
+        /// Value of the `#[path = "..."]` attribute of the declaration, if any
+        path_attribute: Option<String>,
+        /// Files in which the module was looked up
+        expected_paths: Vec<PathBuf>,
+    },
+    /// A module declared with `mod name;` has both a `name.rs` and a `name/mod.rs` file
+    AmbiguousModule { module_name: String, declaring_file: PathBuf, line: usize, found_paths: Vec<PathBuf> },
+    /// A source file is not valid Rust
+    ModuleParse { path: PathBuf, line: usize, message: String },
+    /// The target selected with `--bin` or `--example` does not exist
+    TargetNotFound { kind: String, name: String, available_targets: Vec<String> },
+    /// The version of a registry dependency is not locked in `Cargo.lock`
+    LockedVersionNotFound { name: String, cargo_lock_path: PathBuf },
+    /// The sources of a registry dependency are not in the registry sources folder
+    RegistrySourcesNotFound { name: String, version: String, registry_sources_path: PathBuf },
+    /// Neither `CARGO_HOME` nor the home folder of the user are known
+    CargoHomeNotFound,
+    /// The merged file is over the size limit given with `--max-size`
+    SizeLimitExceeded { path: PathBuf, size: usize, limit: SizeLimit },
+    /// The merged file does not compile
+    CompileErrors { path: PathBuf, errors: Vec<CompileError> },
+    /// The merged binary does not give the expected output on some of the samples
+    SamplesFailed { failed_samples: Vec<String>, sample_count: usize },
+    /// A file or a command could not be read, written or run
+    Io { path: PathBuf, source: io::Error },
+}
+
+/// An error reported by the compiler on the merged file
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompileError {
+    pub message: String,
+    /// Location of the error in the original sources, or in the merged file for generated lines
+    pub location: String,
+}
+
+impl MergeError {
+    /// Build a function wrapping the IO errors about a path, for `map_err`
+    pub(crate) fn io(path: impl AsRef<Path>) -> impl FnOnce(io::Error) -> MergeError {
+        let path = path.as_ref().to_path_buf();
+        move |source| MergeError::Io { path, source }
+    }
+}
+
+impl fmt::Display for MergeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MergeError::PackageRootNotFound { search_start } =>
+                write!(f, "Rust package root not found: no Cargo.toml in {:?} or its parent folders", search_start),
+            MergeError::ManifestParse { path, message } =>
+                write!(f, "Unable to read {:?}: {}", path, message),
+            MergeError::SourceMap { path, message } =>
+                write!(f, "Invalid source map {:?}, merge the package again to rewrite it: {}", path, message),
+            MergeError::ModuleNotFound { module_name, declaring_file, line, path_attribute: Some(path_attribute), expected_paths } =>
+                write!(f, "File not found for module `{}` declared with #[path = \"{}\"] in {:?} at line {}: {:?}",
+                       module_name, path_attribute, declaring_file, line, expected_paths.first().map(PathBuf::as_path).unwrap_or(Path::new(""))),
+            MergeError::ModuleNotFound { module_name, declaring_file, line, path_attribute: None, expected_paths } =>
+                write!(f, "File not found for module `{}` declared in {:?} at line {}, expected {}",
+                       module_name, declaring_file, line, path_list(expected_paths, " or ")),
+            MergeError::AmbiguousModule { module_name, declaring_file, line, found_paths } =>
+                write!(f, "File for module `{}` declared in {:?} at line {} found at both {}", module_name, declaring_file, line, path_list(found_paths, " and ")),
+            MergeError::ModuleParse { path, line, message } =>
+                write!(f, "Unable to parse module file {:?} at line {}: {}", path, line, message),
+            MergeError::TargetNotFound { kind, name, available_targets } =>
+                write!(f, "{} target {} not found, available targets are: {}", kind, name, available_targets.join(", ")),
+            MergeError::LockedVersionNotFound { name, cargo_lock_path } =>
+                write!(f, "Version of registry dependency {} not found in {:?}", name, cargo_lock_path),
+            MergeError::RegistrySourcesNotFound { name, version, registry_sources_path } =>
+                write!(f, "Sources of registry dependency {} {} not found in {:?}", name, version, registry_sources_path),
+            MergeError::CargoHomeNotFound =>
+                write!(f, "Unable to locate the cargo home folder, set the CARGO_HOME environment variable"),
+            MergeError::SizeLimitExceeded { path, size, limit } =>
+                write!(f, "Merged file {:?} is {} {}, over the limit of {} {}", path, size, limit.unit, limit.size, limit.unit),
+            MergeError::CompileErrors { path, errors } => match errors.first() {
+                Some(error) => write!(f, "Merged file {:?} does not compile, {} error(s) found, the first one at {}: {}", path, errors.len(), error.location, error.message),
+                None => write!(f, "Merged file {:?} does not compile", path),
+            },
+            MergeError::SamplesFailed { failed_samples, sample_count } =>
+                write!(f, "{} of the {} samples failed: {}", failed_samples.len(), sample_count, failed_samples.join(", ")),
+            MergeError::Io { path, source } =>
+                write!(f, "{:?}: {}", path, source),
+        }
+    }
+}
+
+impl Error for MergeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            MergeError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+fn path_list(paths: &[PathBuf], separator: &str) -> String {
+    paths.iter().map(|path| format!("{:?}", path)).collect::<Vec<String>>().join(separator)
+}
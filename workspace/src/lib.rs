@@ -1,4 +1,31 @@
+//! Merges the sources of a crate, its library and dependencies included, into a single file.
+//!
+//! The merge is configured with a [`MergeBuilder`], and returns the merged source along with metadata
+//! about what it was built from, without writing any file:
+//! ```
+//! use cargo_merge::MergeBuilder;
+//!
+//! let output = MergeBuilder::new("tests_data/simple_binary").merge().unwrap();
+//! println!("{}", output.source);
+//! ```
+//! The errors are reported as [`MergeError`] values.
+
 #[doc(hidden)]
 pub mod opts;
 #[doc(hidden)]
-pub mod merge;
\ No newline at end of file
+pub mod merge;
+mod builder;
+mod error;
+mod cfg;
+mod check;
+mod minify;
+mod samples;
+mod shake;
+mod size;
+mod source_map;
+mod watch;
+
+pub use builder::{MergeBuilder, MergeOutput, Target};
+pub use error::{CompileError, MergeError};
+pub use opts::TargetProfile;
+pub use source_map::LineOrigin;
@@ -6,14 +6,28 @@
 //!
 //! The initial purpose of this command is to merge your whole crate as a single source file that can be used on competitive programming platforms.
 //!
-//! It works by expanding module imports by detecting them with regex, rewriting some "use" statements in the process.
+//! It works by expanding module imports found by parsing the source code with [syn](https://crates.io/crates/syn), rewriting some "use" statements in the process.
 //!
 //! ## Features
 //! - merge the library crate of the package
 //! - merge modules from the main binary crate of the package
+//! - targets declared with custom names and paths in `Cargo.toml` (`[lib]` and `[[bin]]` sections), and binaries in `src/bin/`
+//! - merge modules declared with a `#[path = "..."]` attribute
 //! - merge external crates
 //! - path based crates (crates that are declared as dependencies like this : `my-crate = { path = "path/to/crate" }` )
+//! - registry based crates (crates.io dependencies, like this : `my-crate = "1.0"` ), from their sources in the local cargo cache or in a vendored folder (opt-in)
+//! - the whole dependency graph is read with `cargo metadata`, so workspaces, renamed and target specific dependencies are supported
 //! - silence the standard error output by cleansing source code from `eprint!` and `eprintln!` macros
+//! - evaluate `#[cfg(...)]`, `#[cfg_attr(...)]` and `cfg!(...)` feature predicates against the selected features (following the `[features]` tables, optional dependencies included), so the merged file no longer depends on features
+//! - evaluate the target (`target_os`, `unix`, `debug_assertions`...) and custom `cfg` predicates for the judge environment
+//! - remove the test items: `#[cfg(test)]` modules (inline or in their own file) and items, and `#[test]` functions
+//! - strip doc comments and `#[doc = "..."]` attributes, examples included
+//! - tree-shake the merged file, removing the items that are not used by the binary, to fit the size limits of the platforms
+//! - minify the merged file, removing comments, doc comments and whitespace while preserving string literals
+//! - check that the merged file compiles, reporting the errors at their file and line in the original sources
+//! - run the merged binary against sample tests (`*.in` inputs and `*.out` expected outputs), exactly as it will be submitted
+//! - write a source map (`target/merge/merged.map.json`) alongside the merged file, to trace its lines back to the original crate, file and line with `cargo merge locate <line>`
+//! - watch mode, merging the package again each time one of its source files changes, and copying the merged file to a fixed path
 //!
 //! ## Examples
 //! Working basic examples can be found in the [integration tests data](https://github.com/jfaixo/cargo-merge/tree/main/workspace/tests_data).
@@ -30,13 +44,40 @@
 //! cargo merge
 //! ```
 //!
-//! This will generate a merged file in `target/merge/merged.rs`.
+//! This will generate a merged file in `target/merge/merged.rs`, or at the path given with `-o <path>`. With `-o -`, the merged file is printed on the standard output, so it can be piped into a submission script: it is only printed once it passes the `--max-size`, `--check` and `--test-samples` validations.
+//!
+//! A source map is written alongside it, in `target/merge/merged.map.json`. When the judge reports an error at a line of the merged file, find where it comes from with:
+//! ```bash
+//! cargo merge locate <line>
+//! ```
 //!
 //! ## Options
 //!
 //! | Long flag | Short flag | Description |
 //! |-|-|-|
+//! | | `--manifest-path <path>` | Path to the `Cargo.toml` of the package to merge, instead of looking it up from the current directory. |
 //! | `-s` | `--silence-standard-error-output` | Remove all the usages of `eprint!` and `eprintln!` macros from your code. |
+//! | `-r` | `--registry-dependencies` | Merge the dependencies coming from a registry, using the versions locked in `Cargo.lock`. |
+//! | | `--registry-sources <path>` | Folder containing the registry dependencies sources (defaults to `~/.cargo/registry/src`), like a vendored folder. |
+//! | | `--bin <name>` | Merge the given binary target, into `target/merge/<name>.rs`. |
+//! | | `--example <name>` | Merge the given example target, into `target/merge/<name>.rs`. |
+//! | | `--all-bins` | Merge every binary target, each one into `target/merge/<name>.rs`. |
+//! | `-o` | `--output <path>` | Write the merged file to `<path>`, or to the standard output with `-o -` (status messages are printed on the standard error output). |
+//! | | `--features <features>` | Features of the package to enable, space or comma separated, the option can be repeated. |
+//! | | `--no-default-features` | Do not enable the `default` feature of the package. |
+//! | | `--cfg <option>` | Configuration option set on the judge, as `name` or `name="value"` (can be repeated). |
+//! | | `--target-profile <profile>` | Platform of the judge: `linux`, `windows` or `macos`, on x86_64 in release mode (no `debug_assertions`). The dependencies declared for other platforms are not merged. |
+//! | | `--keep-tests` | Keep the `#[cfg(test)]` items and `#[test]` functions, that are removed by default. |
+//! | | `--strip-doc-comments` | Remove the doc comments (`///`, `//!`, `/** */`...) and `#[doc = "..."]` attributes, with the examples they contain. |
+//! | | `--tree-shake` | Remove the functions, types, impls, modules and constants that can't be reached from the `main` function. |
+//! | | `--minify` | Remove comments, doc comments, blank lines and unnecessary whitespace from the merged file. |
+//! | | `--max-size <size>` | Fail if the merged file is larger than `<size>` bytes (or chars, with a `chars` suffix like `100000chars`), and report the size of each module. |
+//! | | `--check` | Compile the merged file with `rustc`, and report the errors at their location in the original sources. |
+//! | | `--edition <edition>` | Rust edition used by `--check` and `--test-samples`, the edition of the package by default. |
+//! | | `--test-samples <dir>` | Compile the merged file and run it on each `*.in` file of `<dir>`, comparing its output with the matching `*.out` file (ignoring trailing whitespace). |
+//! | | `--sample-timeout <seconds>` | Time after which the merged binary is stopped and the sample fails, 10 seconds by default. |
+//! | `-w` | `--watch` | Watch the files read by the merge (sources of the package and of the merged dependencies, `Cargo.toml`), and merge again when they change. |
+//! | | `--copy-to <path>` | Copy the merged file to `<path>` after each merge, like a file shared with the clipboard or a submission tool. |
 //!
 //! ## Credits
 //! This little project is heavily inspired by [rust-sourcebundler](https://github.com/lpenz/rust-sourcebundler).
@@ -48,6 +89,7 @@ use structopt::StructOpt;
 use cargo_merge::opts::Opts;
 use cargo_merge::merge::Merge;
 use log::LevelFilter;
+use colored::Colorize;
 
 #[doc(hidden)]
 /// Main entry point
@@ -68,7 +110,10 @@ fn main() {
         simple_logging::log_to_stderr(LevelFilter::Info);
     }
 
-    // Run the merge logic
+    // Run the merge logic, reporting its failure like cargo does
     let merge = Merge::new(opts);
-    merge.run();
+    if let Err(error) = merge.run() {
+        eprintln!("{}: {}", "error".red().bold(), error);
+        std::process::exit(1);
+    }
 }
@@ -1,221 +1,1270 @@
 use std::path::PathBuf;
 use std::path::Path;
-use std::{fs, io};
+use std::fs;
+use std::io::{self, Write};
+use std::cell::RefCell;
+use std::ops::Range;
+use std::time::Duration;
 use toml::Value;
-use log::{debug};
-use crate::opts::Opts;
-use std::io::BufRead;
-use regex::Regex;
-use std::fs::File;
-use std::fmt::Write;
+use log::{debug, warn};
+use crate::opts::{Command, Opts};
+use crate::cfg::{Cfg, CfgEvaluator, CfgOptions};
+use crate::check;
+use crate::error::MergeError;
+use crate::minify;
+use crate::samples;
+use crate::shake;
+use crate::size;
+use crate::watch::{self, FileWatcher};
+use crate::source_map::{LineOrigin, MergedSource, SourceMap};
 use colored::Colorize;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet};
+use cargo_metadata::{DependencyKind, MetadataCommand, Package};
+use proc_macro2::{Ident, Spacing, Span, TokenStream, TokenTree};
+use syn::{Arm, Attribute, Expr, ExprLit, ExprMacro, Field, FieldPat, FieldValue, FnArg, GenericParam, ImplItem, Item, ItemMacro, ItemMod, ItemUse, Lit, Macro, Meta, MetaNameValue, Stmt, StmtMacro, Token, TraitItem, UseTree, Variant, VisRestricted, Visibility};
+use syn::parse::Parser;
+use syn::punctuated::Punctuated;
+use quote::ToTokens;
+use syn::spanned::Spanned;
+use syn::visit::{self, Visit};
 
 const CARGO_TOML : &str = "Cargo.toml";
-const SIMPLE_CRATE_MAIN_RS : &str = "src/main.rs";
-const SIMPLE_CRATE_LIB_RS : &str = "src/lib.rs";
-const SIMPLE_CRATE_MAIN : &str = "src/main";
-const SIMPLE_CRATE_LIB : &str = "src/lib";
+const CARGO_LOCK : &str = "Cargo.lock";
+const CARGO_REGISTRY_SOURCES : &str = "registry/src";
 const MERGE_OUTPUT_PATH: &str = "target/merge/";
 const MERGED_OUTPUT_FILE_NAME: &str = "merged.rs";
+const SOURCE_MAP_EXTENSION: &str = "map.json";
+const STDOUT_OUTPUT: &str = "-";
 
-const REGEX_COMMENT : &str = r"^\s*//";
-const REGEX_MOD : &str = r"^\s*(pub\s+)?mod\s+(.*)\s*;\s*$";
-const REGEX_USE : &str = r"^\s*use\s+(.*)\s*$";
-const REGEX_EPRINT: &str = r"^\s*eprint(ln)?!";
+const EPRINT_MACROS: [&str; 2] = ["eprint", "eprintln"];
 
 pub struct Merge {
-    comment_regex: Regex,
-    mod_regex: Regex,
-    use_regex: Regex,
-    eprint_regex: Regex,
-    opts: Opts,
+    pub(crate) opts: Opts,
+    /// Configuration options against which the `cfg` attributes of all the crates are evaluated
+    cfg_options: CfgOptions,
+    /// Files read by the merge, watched for changes in watch mode
+    visited_files: RefCell<BTreeSet<PathBuf>>,
 }
 
-struct CargoData {
-    package_name: String,
-    external_crates: HashMap<String, PathBuf>
+pub(crate) struct CargoData {
+    pub(crate) package_name: String,
+    /// The lib crate of the package, merged in a module named as the lib target
+    lib_crate: Option<CrateData>,
+    /// The binary targets of the package
+    pub(crate) bin_targets: Vec<BinTarget>,
+    /// The example targets of the package, that are binaries too
+    example_targets: Vec<BinTarget>,
+    /// The binary target run by default, as declared in the manifest
+    default_run: Option<String>,
+    /// Dependencies of the package, by the name they are used with in its source, mapped to the module they are merged into
+    dependencies: BTreeMap<String, String>,
+    /// Features enabled for the package
+    pub(crate) features: BTreeSet<String>,
+    /// Rust edition of the package
+    pub(crate) edition: String,
+    /// All the external crates of the dependency graph of the package, by the name of the module they are merged into
+    external_crates: BTreeMap<String, CrateData>,
+}
+
+impl CargoData {
+    /// Names of the modules the library crates are merged into: the dependency crates, then the lib crate of the package
+    pub(crate) fn library_module_names(&self) -> Vec<String> {
+        self.external_crates.values().chain(&self.lib_crate).map(|library_crate| library_crate.name.clone()).collect()
+    }
+}
+
+/// A binary (or example) target of the package
+pub(crate) struct BinTarget {
+    pub(crate) name: String,
+    /// Root source file of the binary crate
+    root_path: PathBuf,
+}
+
+/// A crate to merge
+struct CrateData {
+    /// Name of the module the crate is merged into, empty for the crate merged at the root
+    name: String,
+    /// Name of the crate, to tell where the merged lines come from
+    crate_name: String,
+    /// Root source file of the crate
+    root_path: PathBuf,
+    /// Dependencies of the crate, by the name they are used with in its source, mapped to the module they are merged into
+    dependencies: BTreeMap<String, String>,
+    /// Features enabled for the crate, against which its `cfg` attributes are evaluated
+    features: BTreeSet<String>,
+}
+
+/// Features requested for a package, by the command line or by the packages depending on it
+#[derive(Clone, Default, PartialEq)]
+struct FeatureRequest {
+    features: BTreeSet<String>,
+    default_features: bool,
+}
+
+/// A dependency crate loaded into the module named after it, and the features requested for it
+struct LoadedCrate {
+    version: String,
+    feature_request: FeatureRequest,
+}
+
+/// Features enabled for a package, and what they enable in its dependencies
+#[derive(Default)]
+struct EnabledFeatures {
+    features: BTreeSet<String>,
+    /// Optional dependencies enabled, by their name in the manifest
+    optional_dependencies: BTreeSet<String>,
+    /// Features enabled in the dependencies, by the dependency name in the manifest
+    dependency_features: BTreeMap<String, BTreeSet<String>>,
 }
 
 impl Merge {
     pub fn new(opts: Opts) -> Merge {
         Merge {
-            comment_regex: Regex::new(REGEX_COMMENT).expect("Unable to compile the comment regex"),
-            mod_regex: Regex::new(REGEX_MOD).expect("Unable to compile the mod regex"),
-            use_regex: Regex::new(REGEX_USE).expect("Unable to compile the use regex"),
-            eprint_regex: Regex::new(REGEX_EPRINT).expect("Unable to compile the eprint regex"),
+            cfg_options: CfgOptions::new(&opts),
+            visited_files: RefCell::new(BTreeSet::new()),
             opts
         }
     }
 
     /// Main merge entrypoint
-    pub fn run(&self) {
-        // Detect the package root
-        let package_root_path = detect_package_root();
+    pub fn run(&self) -> Result<(), MergeError> {
+        if let Some(Command::Locate { line, map }) = &self.opts.command {
+            self.print_location(*line, map.as_deref())
+        } else if self.opts.watch {
+            self.watch()
+        } else {
+            self.merge()
+        }
+    }
+
+    /// Merge the package again each time one of the files it is made of changes, until the process is stopped
+    fn watch(&self) -> Result<(), MergeError> {
+        loop {
+            self.visited_files.borrow_mut().clear();
+            // A failed merge is reported like any other, and merged again once the sources are fixed
+            let result = self.merge();
+            let mut watched_files = self.visited_files();
+            if watched_files.is_empty() {
+                // The merge failed before reading any file, the manifest and the sources of the package are watched instead
+                let package_root_path = self.package_root_path().or_else(|_| current_dir())?;
+                watched_files.push(package_root_path.join(CARGO_TOML));
+                watched_files.extend(watch::folder_files(&package_root_path.join("src")));
+            }
+            let watcher = FileWatcher::new(watched_files);
+            match result {
+                Ok(()) => eprintln!("    {} {} files for changes (press Ctrl+C to stop)", "Watching".green().bold(), watcher.len()),
+                Err(error) => {
+                    eprintln!("{}: {}", "error".red().bold(), error);
+                    eprintln!("    {} {} files, the merge failed (press Ctrl+C to stop)", "Watching".red().bold(), watcher.len());
+                }
+            }
+            let changed_files = watcher.wait_for_changes();
+            for changed_file in changed_files {
+                eprintln!("     {} {}", "Changed".yellow().bold(), changed_file.display());
+            }
+        }
+    }
 
-        // Set the package root as the current directory. This is required for dependencies relative paths to be valid.
-        std::env::set_current_dir(&package_root_path).unwrap();
+    /// Merge the package sources, as configured by the options
+    fn merge(&self) -> Result<(), MergeError> {
+        let package_root_path = self.package_root_path()?;
 
-        // Read into the Cargo.toml the package name, which is also the default crate name
-        let cargo_data = load_cargo_toml(&package_root_path);
+        // Locate the registry dependencies sources, the given path being relative to the current directory
+        let registry_sources_path = if self.opts.merge_registry_dependencies {
+            Some(match &self.opts.registry_sources {
+                Some(registry_sources) => current_dir()?.join(registry_sources),
+                None => default_registry_sources_path()?,
+            })
+        } else {
+            None
+        };
+        let test_samples_path = self.opts.test_samples.as_ref().map(|test_samples| current_dir().map(|current_dir| current_dir.join(test_samples))).transpose()?;
+
+        let copy_path = self.opts.copy_to.as_ref().map(|copy_to| current_dir().map(|current_dir| current_dir.join(copy_to))).transpose()?;
+        let output_file_path = self.output_file_path()?;
+
+        let cargo_data = self.load_package(&package_root_path, registry_sources_path.as_deref())?;
+
+        eprintln!("     {} crate {} ({})", "Merging".green().bold(), cargo_data.package_name, package_root_path.display());
+
+        // Holds the output shared by all the binaries: the library part of the merged file
+        let library_output = self.merge_library(&cargo_data)?;
+
+        // Ensure that the folders are created
+        let output_path = package_root_path.join(MERGE_OUTPUT_PATH);
+        fs::create_dir_all(&output_path).map_err(MergeError::io(&output_path))?;
+
+        if self.opts.all_bins {
+            // One file per binary, named after it, all sharing the library part
+            if cargo_data.bin_targets.is_empty() {
+                warn!("No binary target found in crate {}", cargo_data.package_name);
+            }
+            for bin_target in &cargo_data.bin_targets {
+                let output = self.merge_bin(&cargo_data, &library_output, Some(bin_target))?;
+                self.write_output(&output_path.join(format!("{}.rs", bin_target.name)), output, &cargo_data, Some(bin_target), test_samples_path.as_deref(), copy_path.as_deref())?;
+            }
+        }
+        else {
+            // Bin crate case, merged at the root
+            let bin_target = select_bin_target(&cargo_data, &self.opts)?;
+            let output = self.merge_bin(&cargo_data, &library_output, bin_target)?;
 
-        println!("     {} crate {} ({})", "Merging".green().bold(), cargo_data.package_name, package_root_path.to_str().unwrap());
+            let output_file_path = output_file_path.unwrap_or_else(|| output_path.join(self.output_file_name()));
+            self.write_output(&output_file_path, output, &cargo_data, bin_target, test_samples_path.as_deref(), copy_path.as_deref())?;
+        }
+        Ok(())
+    }
 
-        // Holds the single file output built
-        let mut output_string = String::new();
+    /// Read the package metadata: its name, which is also the default crate name, its targets and its dependencies
+    pub(crate) fn load_package(&self, package_root_path: &Path, registry_sources_path: Option<&Path>) -> Result<CargoData, MergeError> {
+        self.visited_files.borrow_mut().insert(package_root_path.join(CARGO_TOML));
+        let feature_request = FeatureRequest {
+            features: self.opts.features.iter()
+                .flat_map(|features| features.split(|c: char| c == ',' || c.is_whitespace()))
+                .filter(|feature| !feature.is_empty())
+                .map(String::from)
+                .collect(),
+            default_features: !self.opts.no_default_features,
+        };
+        load_cargo_data(package_root_path, registry_sources_path, self.opts.example.is_some(), &feature_request, &self.cfg_options)
+    }
 
-        // Merge all the identified dependency crates
-        for dependency in &cargo_data.external_crates {
-            let curated_dependency_name = dependency.0.replace("-", "_");
-            writeln!(output_string, "pub mod {} {{", curated_dependency_name).unwrap();
-            writeln!(output_string, "{}", self.inject_crate(dependency.1.clone(), curated_dependency_name.as_str(), &cargo_data).as_str()).unwrap();
-            writeln!(output_string, "}}").unwrap();
+    /// Merge the library part of the merged files, shared by all the binaries:
+    /// all the identified dependency crates, then the lib crate of this package if there is one
+    pub(crate) fn merge_library(&self, cargo_data: &CargoData) -> Result<MergedSource, MergeError> {
+        let mut library_output = MergedSource::default();
+        for library_crate in cargo_data.external_crates.values().chain(&cargo_data.lib_crate) {
+            library_output.push_line(&format!("pub mod {} {{", library_crate.name), None);
+            library_output.append(&self.inject_crate(library_crate)?);
+            library_output.push_line("", None);
+            library_output.push_line("}", None);
         }
+        Ok(library_output)
+    }
 
-        // If there is a lib crate in this package, process it
-        if Path::new(SIMPLE_CRATE_LIB_RS).exists() {
-            writeln!(output_string, "pub mod {} {{", cargo_data.package_name).unwrap();
-            writeln!(output_string, "{}", self.inject_crate(PathBuf::from(SIMPLE_CRATE_LIB), cargo_data.package_name.as_str(), &cargo_data).as_str()).unwrap();
-            writeln!(output_string, "}}").unwrap();
+    /// Build a merged file from the library part and a binary crate, merged at the root, then prune and minify it if asked
+    pub(crate) fn merge_bin(&self, cargo_data: &CargoData, library_output: &MergedSource, bin_target: Option<&BinTarget>) -> Result<MergedSource, MergeError> {
+        let mut output = library_output.clone();
+        if let Some(bin_target) = bin_target {
+            output.append(&self.inject_bin(bin_target, cargo_data)?);
+        }
+        if self.opts.tree_shake {
+            output = output.derive(shake::remove_unused_items(&output.text));
         }
-        // Simple bin crate case
-        if Path::new(SIMPLE_CRATE_MAIN_RS).exists() {
-            writeln!(output_string, "{}", self.inject_crate(PathBuf::from(SIMPLE_CRATE_MAIN), "", &cargo_data).as_str()).unwrap();
+        if self.opts.minify {
+            output = output.derive(minify::minify(&output.text));
         }
+        Ok(output)
+    }
 
-        // Ensure that the folders are created
-        let output_path = package_root_path.join(MERGE_OUTPUT_PATH);
-        fs::create_dir_all(&output_path).unwrap_or_else(|_| panic!("Unable to create directory: {:?}", output_path));
+    /// Files read by the last merge
+    pub(crate) fn visited_files(&self) -> Vec<PathBuf> {
+        self.visited_files.borrow().iter().cloned().collect()
+    }
+
+    /// Root folder of the package to merge, given with `--manifest-path` or else detected from the current directory
+    fn package_root_path(&self) -> Result<PathBuf, MergeError> {
+        match &self.opts.manifest_path {
+            Some(manifest_path) => package_root(manifest_path),
+            None => detect_package_root(),
+        }
+    }
+
+    /// Path of the single merged file given with `-o`, relative to the current directory.
+    /// When the output is the standard output, the merged file is still written in the merge folder.
+    fn output_file_path(&self) -> Result<Option<PathBuf>, MergeError> {
+        self.opts.output.as_ref()
+            .filter(|_| !self.output_to_stdout())
+            .map(|output| current_dir().map(|current_dir| current_dir.join(output)))
+            .transpose()
+    }
+
+    fn output_to_stdout(&self) -> bool {
+        self.opts.output.as_deref() == Some(Path::new(STDOUT_OUTPUT))
+    }
 
-        // Write to disk
-        let output_file_path = output_path.join(MERGED_OUTPUT_FILE_NAME);
-        fs::write(&output_file_path, output_string)
-            .unwrap_or_else(|_| panic!("There was an issue while writing to file: {}", MERGE_OUTPUT_PATH));
+    /// Name of the single merged file: when a target is selected, the output is named after it
+    fn output_file_name(&self) -> String {
+        match self.opts.bin.as_ref().or(self.opts.example.as_ref()) {
+            Some(target_name) => format!("{}.rs", target_name),
+            None => String::from(MERGED_OUTPUT_FILE_NAME),
+        }
+    }
+
+    /// Print the original file and line of a line of the merged file
+    fn print_location(&self, line: usize, map_path: Option<&Path>) -> Result<(), MergeError> {
+        match self.locate(line, map_path)? {
+            Some(origin) => println!("{}:{} (crate {})", origin.file_path.display(), origin.line, origin.crate_name),
+            None => println!("Line {} of the merged file does not come from the original sources, it was generated by the merge", line),
+        }
+        Ok(())
+    }
 
-        println!("      {} crate {} into `{}` ", "Merged".green().bold(), cargo_data.package_name, output_file_path.to_str().unwrap());
+    /// Find where a line of the merged file comes from, with the source map written alongside it:
+    /// the one given, or else the one of the merged file the options select.
+    /// Lines generated by the merge have no origin.
+    pub fn locate(&self, line: usize, map_path: Option<&Path>) -> Result<Option<LineOrigin>, MergeError> {
+        let map_path = match (map_path, self.output_file_path()?) {
+            (Some(map_path), _) => map_path.to_path_buf(),
+            (None, Some(output_file_path)) => output_file_path.with_extension(SOURCE_MAP_EXTENSION),
+            (None, None) => self.package_root_path()?.join(MERGE_OUTPUT_PATH).join(self.output_file_name()).with_extension(SOURCE_MAP_EXTENSION),
+        };
+        // The source map is missing when the package was not merged yet
+        let source_map = fs::read_to_string(&map_path).map_err(MergeError::io(&map_path))?;
+        let source_map: SourceMap = serde_json::from_str(&source_map)
+            .map_err(|error| MergeError::SourceMap { path: map_path.clone(), message: error.to_string() })?;
+        Ok(source_map.locate(line))
     }
 
-    fn inject_crate(&self, crate_path: PathBuf, package_name: &str, cargo_data: &CargoData) -> String {
-        self.inject_modules(crate_path, package_name, "crate", true, &cargo_data)
+    /// Merge a binary crate, at the root of the output
+    fn inject_bin(&self, bin_target: &BinTarget, cargo_data: &CargoData) -> Result<MergedSource, MergeError> {
+        debug!("Merging binary target {} from: {:?}", bin_target.name, bin_target.root_path);
+        // The lib crate of the package is a dependency of its binaries, used by its name
+        let mut dependencies = cargo_data.dependencies.clone();
+        if let Some(lib_crate) = &cargo_data.lib_crate {
+            dependencies.insert(lib_crate.crate_name.clone(), lib_crate.name.clone());
+        }
+        let bin_crate = CrateData {
+            name: String::new(),
+            crate_name: bin_target.name.replace("-", "_"),
+            root_path: bin_target.root_path.clone(),
+            dependencies,
+            features: cargo_data.features.clone(),
+        };
+        let mut output = self.inject_crate(&bin_crate)?;
+        output.push_line("", None);
+        Ok(output)
     }
 
+    /// Write a merged file to disk, the binary merged at its root if any
+    fn write_output(&self, output_file_path: &Path, output: MergedSource, cargo_data: &CargoData, bin_target: Option<&BinTarget>, test_samples_path: Option<&Path>, copy_path: Option<&Path>) -> Result<(), MergeError> {
+        let output_string = &output.text;
+
+        fs::write(output_file_path, output_string).map_err(MergeError::io(output_file_path))?;
 
-    /// Inject a module into the output file, recursively injecting nested modules
-    fn inject_modules(&self, full_module_path: PathBuf, current_module_name: &str, full_module_name: &str, is_root_module: bool, cargo_data: &CargoData) -> String {
-        let mut output_string = String::new();
+        // The source map is written alongside, to trace the lines reported by the judge back to the original sources
+        let source_map_path = output_file_path.with_extension(SOURCE_MAP_EXTENSION);
+        let source_map = output.source_map(&output_file_path.file_name().unwrap().to_string_lossy());
+        fs::write(&source_map_path, serde_json::to_string_pretty(&source_map).unwrap()).map_err(MergeError::io(&source_map_path))?;
 
-        // Find whether this module is defined with a lib.rs file, or directly by a file named as the module
-        let mut possible_module_file_paths = Vec::new();
+        eprintln!("      {} crate {} into `{}` ", "Merged".green().bold(), cargo_data.package_name, output_file_path.display());
 
-        // Rust file case
-        let mut rs_file = full_module_path.clone();
-        if !is_root_module {
-            rs_file.set_file_name(current_module_name);
+        if let Some(copy_path) = copy_path {
+            fs::copy(output_file_path, copy_path).map_err(MergeError::io(copy_path))?;
+            eprintln!("      {} merged file to `{}`", "Copied".green().bold(), copy_path.display());
         }
-        rs_file.set_extension("rs");
-        possible_module_file_paths.push(rs_file);
 
-        // mod.rs file case
-        let mut mod_file = full_module_path.clone();
-        mod_file.set_file_name("mod.rs");
-        possible_module_file_paths.push(mod_file);
+        if let Some(max_size) = self.opts.max_size {
+            let output_size = max_size.unit.measure(output_string);
+            if output_size > max_size.size {
+                // Report where the size comes from, so that the largest modules can be trimmed
+                eprintln!("   {} merged file is {} {}, over the limit of {} {}", "Oversized".red().bold(), output_size, max_size.unit, max_size.size, max_size.unit);
+                for module_size in size::module_sizes(output_string, max_size.unit) {
+                    let module_path = if module_size.path.is_empty() { "(root)" } else { module_size.path.as_str() };
+                    eprintln!("{:>16} {}  {}", module_size.size, max_size.unit, module_path);
+                }
+                return Err(MergeError::SizeLimitExceeded { path: output_file_path.to_path_buf(), size: output_size, limit: max_size });
+            }
+        }
 
-        // Find if any one exists
-        let module_file_descriptor = possible_module_file_paths.iter().map(|possible_module_file_paths| {
-                debug!("Trying to open: {:?}", possible_module_file_paths);
-                File::open(possible_module_file_paths)
-            })
-            .find(|file_descriptor| { file_descriptor.is_ok() });
-
-        match module_file_descriptor {
-            Some(module_file_descriptor) => {
-                // Use the found file, read and inject it
-                let module_file_descriptor = module_file_descriptor.unwrap_or_else(|_| panic!("Unable to open module file: {:?}", full_module_path));
-                let lines = io::BufReader::new(module_file_descriptor).lines();
-                for line in lines {
-                    if let Ok(line) = line {
-                        if self.comment_regex.is_match(&line) {
-                            // If the line is a comment
-                            writeln!(output_string, "{}", line).unwrap();
-                        }
-                        // ##### use declaration rewrite
-                        else if let Some(module_name) = self.use_regex.captures(&line) {
-                            // If the line is a use declaration, rewrite it
-                            let module_name = module_name.get(1).unwrap().as_str().trim();
-                            debug!("found use declaration: {}", module_name);
-                            let mut modified_module_name = module_name.replace("crate", full_module_name);
-
-                            // Handle the use declaration of external dependencies declared in Cargo.toml
-                            for dependency in &cargo_data.external_crates {
-                                if modified_module_name.starts_with(dependency.0.replace("-", "_").as_str()) {
-                                    modified_module_name = format!("crate::{}", modified_module_name);
-                                }
-                            }
+        if self.opts.check {
+            let edition = self.opts.edition.as_deref().unwrap_or(&cargo_data.edition);
+            // Without a binary, there is no main function and the merged file is a library
+            let crate_type = if bin_target.is_some() { "bin" } else { "lib" };
+            let errors = check::check(output_file_path, &output, edition, crate_type)?;
+            for error in &errors {
+                eprintln!("{}: {}", "error".red().bold(), error.message);
+                eprintln!("  --> {}", error.location);
+            }
+            if !errors.is_empty() {
+                return Err(MergeError::CompileErrors { path: output_file_path.to_path_buf(), errors });
+            }
+            eprintln!("     {} merged file with rustc (edition {})", "Checked".green().bold(), edition);
+        }
+
+        if let Some(test_samples_path) = test_samples_path {
+            let edition = self.opts.edition.as_deref().unwrap_or(&cargo_data.edition);
+            let timeout = match self.opts.sample_timeout.map(Duration::try_from_secs_f64) {
+                Some(Ok(timeout)) => timeout,
+                Some(Err(error)) => {
+                    warn!("Invalid sample timeout {:?}, using the default one: {}", self.opts.sample_timeout, error);
+                    samples::DEFAULT_SAMPLE_TIMEOUT
+                }
+                None => samples::DEFAULT_SAMPLE_TIMEOUT,
+            };
+            let results = samples::run_samples(output_file_path, test_samples_path, edition, timeout)?;
+            for result in &results {
+                match &result.mismatch {
+                    None => eprintln!("     {} sample {}", "Passed".green().bold(), result.name),
+                    Some(mismatch) => eprintln!("     {} sample {}, {}", "Failed".red().bold(), result.name, mismatch),
+                }
+            }
+            let failed_samples: Vec<String> = results.iter().filter(|result| result.mismatch.is_some()).map(|result| result.name.clone()).collect();
+            if !failed_samples.is_empty() {
+                return Err(MergeError::SamplesFailed { failed_samples, sample_count: results.len() });
+            }
+        }
+
+        // The standard output is usually piped to a submission tool, it only gets a merged file that passed the validations
+        if self.output_to_stdout() {
+            io::stdout().write_all(output_string.as_bytes()).map_err(MergeError::io(STDOUT_OUTPUT))?;
+        }
+        Ok(())
+    }
+
+    fn inject_crate(&self, crate_data: &CrateData) -> Result<MergedSource, MergeError> {
+        // Path under which the crate root is reachable once merged
+        let crate_module_path = if crate_data.name.is_empty() {
+            String::from("crate")
+        } else {
+            format!("crate::{}", crate_data.name)
+        };
+        // Nested module files of a crate root are located next to it
+        let module_dir = crate_data.root_path.parent().map(Path::to_path_buf).unwrap_or_default();
+        self.inject_modules(&crate_data.root_path, module_dir, crate_module_path.as_str(), crate_data)
+    }
+
+    /// Inject a module file into the output file, recursively injecting nested modules, that are looked up in `module_dir`
+    fn inject_modules(&self, module_file_path: &Path, module_dir: PathBuf, crate_module_path: &str, crate_data: &CrateData) -> Result<MergedSource, MergeError> {
+        self.visited_files.borrow_mut().insert(module_file_path.to_path_buf());
+        let source = fs::read_to_string(module_file_path).map_err(MergeError::io(module_file_path))?;
+        let source = source.trim_start_matches('\u{feff}');
+        let syntax_tree = syn::parse_file(source)
+            .map_err(|error| MergeError::ModuleParse { path: module_file_path.to_path_buf(), line: error.span().start().line, message: error.to_string() })?;
+
+        let mut collector = EditCollector {
+            merge: self,
+            source,
+            offset: shebang_offset(&syntax_tree),
+            file_path: module_file_path,
+            module_dir,
+            inline_depth: 0,
+            crate_module_path,
+            crate_data,
+            edits: Vec::new(),
+            expanded_modules: BTreeMap::new(),
+            error: None,
+        };
+        collector.visit_file(&syntax_tree);
+        if let Some(error) = collector.error {
+            return Err(error);
+        }
+
+        // Output the rewritten source, line by line
+        let origin = |line| LineOrigin {
+            crate_name: crate_data.crate_name.clone(),
+            file_path: module_file_path.to_path_buf(),
+            line,
+        };
+        let edited_source = apply_module_edits(source, collector.edits, &collector.expanded_modules, origin);
+        let mut output = MergedSource::default();
+        for (line, origin) in edited_source.text.lines().zip(edited_source.origins) {
+            output.push_line(line, origin);
+        }
+        Ok(output)
+    }
+}
+
+/// A replacement of a byte range of a source file
+pub(crate) struct SourceEdit {
+    pub(crate) range: Range<usize>,
+    pub(crate) replacement: String,
+}
+
+/// Walks the syntax tree of a module file and collects the edits to apply to its source:
+/// nested module declarations to expand, `use` declarations to rewrite, `cfg` attributes to evaluate and `eprint!` calls to remove.
+struct EditCollector<'a> {
+    merge: &'a Merge,
+    source: &'a str,
+    offset: usize,
+    /// Path of the module file visited
+    file_path: &'a Path,
+    /// Folder in which the nested module files of the module currently visited are looked up
+    module_dir: PathBuf,
+    /// Number of inline modules (`mod name { ... }`) enclosing the items currently visited
+    inline_depth: usize,
+    crate_module_path: &'a str,
+    crate_data: &'a CrateData,
+    edits: Vec<SourceEdit>,
+    /// Expanded module declarations, by the start of their range, with the origins of the lines replacing them
+    expanded_modules: BTreeMap<usize, Vec<Option<LineOrigin>>>,
+    /// First error met, that stops the merge once the module is visited
+    error: Option<MergeError>,
+}
 
-                            debug!("rewriting statement to: {}", modified_module_name);
-                            writeln!(output_string, "use {}", modified_module_name).unwrap();
+impl<'a> EditCollector<'a> {
+    /// Convert a span into a byte range of the source
+    fn range(&self, span: Span) -> Range<usize> {
+        let range = span.byte_range();
+        range.start + self.offset..range.end + self.offset
+    }
+
+    fn push_edit(&mut self, range: Range<usize>, replacement: String) {
+        self.edits.push(SourceEdit { range, replacement });
+    }
+
+    /// Find the file of a module declared without a `#[path]` attribute, following rustc lookup rules:
+    /// the module `name` is either in `name.rs` or in `name/mod.rs`, relative to the current module folder
+    fn resolve_module_file(&self, module_name: &str, line: usize) -> Result<PathBuf, MergeError> {
+        let rs_file = self.module_dir.join(format!("{}.rs", module_name));
+        let mod_file = self.module_dir.join(module_name).join("mod.rs");
+        debug!("Looking up module {} in: {:?} or {:?}", module_name, rs_file, mod_file);
+
+        let (rs_file_found, mod_file_found) = (rs_file.is_file(), mod_file.is_file());
+        if rs_file_found == mod_file_found {
+            // In watch mode, the merge is run again once one of the candidate files is created or removed
+            self.merge.visited_files.borrow_mut().extend([rs_file.clone(), mod_file.clone()]);
+        }
+        match (rs_file_found, mod_file_found) {
+            (true, false) => Ok(rs_file),
+            (false, true) => Ok(mod_file),
+            (true, true) => Err(MergeError::AmbiguousModule {
+                module_name: module_name.to_string(),
+                declaring_file: self.file_path.to_path_buf(),
+                line,
+                found_paths: vec![rs_file, mod_file],
+            }),
+            (false, false) => Err(MergeError::ModuleNotFound {
+                module_name: module_name.to_string(),
+                declaring_file: self.file_path.to_path_buf(),
+                line,
+                path_attribute: None,
+                expected_paths: vec![rs_file, mod_file],
+            }),
+        }
+    }
+
+    /// Folder against which a `#[path]` attribute of a module declared at the current location is resolved
+    fn path_attribute_base(&self) -> PathBuf {
+        if self.inline_depth == 0 {
+            // Relative to the folder of the declaring file
+            self.file_path.parent().map(Path::to_path_buf).unwrap_or_default()
+        } else {
+            // Inside inline modules, relative to the folder their nested module files would be in
+            self.module_dir.clone()
+        }
+    }
+
+    /// Rewrite the paths starting at the root of a use tree, so that they stay valid once merged.
+    /// Groups found at the root (`use {a::b, c::d};`) are walked, as each of their items is itself a root path.
+    fn rewrite_use_tree(&mut self, tree: &UseTree, leading_colon: Option<&Token![::]>) {
+        let ident = match tree {
+            UseTree::Path(use_path) => &use_path.ident,
+            UseTree::Name(use_name) => &use_name.ident,
+            UseTree::Rename(use_rename) => &use_rename.ident,
+            UseTree::Group(use_group) => {
+                // Items of a `::{...}` group are extern crates, that are not rewritten
+                if leading_colon.is_none() {
+                    for item in &use_group.items {
+                        self.rewrite_use_tree(item, None);
+                    }
+                }
+                return;
+            }
+            UseTree::Glob(_) => return,
+        };
+
+        self.rewrite_root_segment(ident, leading_colon);
+    }
+
+    /// Rewrite the first segment of a path, so that it stays valid once merged.
+    /// Only whole segments are considered: `crate_tools` or `my_crate` are left as is, and so are the paths relative
+    /// to the current module (`self::...`, `super::...`).
+    fn rewrite_root_segment(&mut self, ident: &Ident, leading_colon: Option<&Token![::]>) {
+        let ident_range = self.range(ident.span());
+        if ident == "crate" {
+            // The crate root is now a module of the merged crate
+            if self.crate_module_path != "crate" {
+                debug!("rewriting crate root to: {}", self.crate_module_path);
+                self.push_edit(ident_range, self.crate_module_path.to_string());
+            }
+        }
+        // Handle the paths to the dependencies of the crate
+        else if let Some(dependency_module_name) = self.crate_data.dependencies.get(&ident.to_string()) {
+            // Their root module is declared at the root of the merged crate
+            let range = match leading_colon {
+                Some(leading_colon) => self.range(leading_colon.span()).start..ident_range.end,
+                None => ident_range,
+            };
+            debug!("rewriting external crate path: crate::{}", dependency_module_name);
+            self.push_edit(range, format!("crate::{}", dependency_module_name));
+        }
+    }
+
+    fn cfg_evaluator(&self) -> CfgEvaluator<'a> {
+        CfgEvaluator {
+            features: &self.crate_data.features,
+            keep_tests: self.merge.opts.keep_tests,
+            cfg_options: &self.merge.cfg_options,
+        }
+    }
+
+    /// Evaluate the `cfg` and `cfg_attr` attributes of a node, and remove it if it is configured out (or is a test).
+    /// Returns whether the node is kept, in which case the attributes that could be evaluated are removed or simplified.
+    fn configure(&mut self, attributes: &[Attribute], span: Span, is_punctuated: bool) -> bool {
+        let evaluator = self.cfg_evaluator();
+        let mut attribute_edits = Vec::new();
+        let mut is_kept = true;
+        for attribute in attributes {
+            let range = self.range(attribute.span());
+            match &attribute.meta {
+                Meta::Path(path) if path.is_ident("test") && !self.merge.opts.keep_tests => is_kept = false,
+                Meta::List(list) if list.path.is_ident("cfg") => match evaluator.evaluate_tokens(list.tokens.clone()) {
+                    Cfg::True => attribute_edits.push(SourceEdit { range: expand_to_lines_or_spaces(self.source, range), replacement: String::new() }),
+                    Cfg::False => is_kept = false,
+                    Cfg::Unknown(predicate) => {
+                        let predicate = self.tokens_text(&predicate);
+                        if predicate != self.tokens_text(&list.tokens) {
+                            attribute_edits.push(SourceEdit { range, replacement: format!("#[cfg({})]", predicate) });
                         }
-                        // ##### mod declaration rewrite
-                        else if let Some(module_name) = self.mod_regex.captures(&line) {
-                            // If the line is a module import, process it
-                            let module_name = module_name.get(2).unwrap().as_str().trim();
-                            // Open the module closure
-                            writeln!(output_string, "pub mod {} {{", module_name).unwrap();
-
-                            // Inject the module content recursively
-                            let full_module_path = if is_root_module {
-                                let mut path = PathBuf::new();
-                                path.push(&full_module_path);
-                                path.pop();
-                                path.push(module_name);
-                                path
-                            }
-                            else {
-                                Path::new(&full_module_path).join(module_name)
-                            };
-
-                            let full_module_name = if is_root_module {
-                                format!("{}::{}", full_module_name, current_module_name)
-                            } else {
-                                full_module_name.to_string()
-                            };
-                            writeln!(output_string, "{}", self.inject_modules(full_module_path, &module_name, full_module_name.as_str(), false, cargo_data)).unwrap();
-
-                            // Close the closure
-                            writeln!(output_string, "}}").unwrap();
+                    }
+                },
+                Meta::List(list) if list.path.is_ident("cfg_attr") => {
+                    let arguments = match Punctuated::<Meta, Token![,]>::parse_terminated.parse2(list.tokens.clone()) {
+                        Ok(arguments) => arguments,
+                        Err(_) => continue,
+                    };
+                    let mut arguments = arguments.iter();
+                    let predicate = match arguments.next() {
+                        Some(predicate) => predicate,
+                        None => continue,
+                    };
+                    let attributes: Vec<String> = arguments.map(|argument| self.tokens_text(&argument.to_token_stream())).collect();
+                    match evaluator.evaluate(predicate) {
+                        Cfg::True => {
+                            let replacement = attributes.iter().map(|attribute| format!("#[{}]", attribute)).collect::<Vec<String>>().join(" ");
+                            attribute_edits.push(SourceEdit { range, replacement });
                         }
-                        else if self.eprint_regex.is_match(&line) {
-                            // Output the line only if the flag to remove error printing is not set
-                            if !self.opts.remove_error_output {
-                                writeln!(output_string, "{}", line).unwrap();
+                        Cfg::False => attribute_edits.push(SourceEdit { range: expand_to_lines_or_spaces(self.source, range), replacement: String::new() }),
+                        Cfg::Unknown(predicate) => {
+                            let predicate = self.tokens_text(&predicate);
+                            if predicate != self.tokens_text(&predicate_tokens(&list.tokens)) {
+                                attribute_edits.push(SourceEdit { range, replacement: format!("#[cfg_attr({}, {})]", predicate, attributes.join(", ")) });
                             }
                         }
-                        else {
-                            // Just output the line
-                            writeln!(output_string, "{}", line).unwrap();
-                        }
                     }
                 }
+                _ => {}
+            }
+        }
 
-                output_string
+        if is_kept {
+            self.edits.extend(attribute_edits);
+        } else {
+            // Remove the whole node, and the comma separating it from the next one in a list (fields, variants...),
+            // or else from the previous one when it ends a list on a single line
+            let mut range = self.range(span);
+            if is_punctuated {
+                let rest = &self.source[range.end..];
+                let trimmed_rest = rest.trim_start();
+                let preceding = self.source[..range.start].trim_end();
+                if trimmed_rest.starts_with(',') {
+                    range.end += rest.len() - trimmed_rest.len() + 1;
+                } else if preceding.ends_with(',') && !self.source[preceding.len()..range.start].contains('\n') {
+                    range.start = preceding.len() - 1;
+                }
             }
-            None => {
-                panic!("File not found for module: {:?}", full_module_path);
+            // The blank lines around go along. When there are only blank lines before, the removed lines just before them
+            // go along too, up to the blank lines preceding them
+            let lines = expand_to_lines_or_spaces(self.source, range);
+            let mut range = expand_to_blank_lines(self.source, lines.clone());
+            while range.end == lines.end {
+                let previous_start = self.edits.iter()
+                    .find(|edit| edit.replacement.is_empty() && edit.range.start < range.start && edit.range.end >= range.start
+                        && expand_to_lines(self.source, edit.range.clone()) == edit.range)
+                    .map(|edit| edit.range.start);
+                match previous_start {
+                    Some(previous_start) => range = expand_to_blank_lines(self.source, previous_start..range.end),
+                    None => break,
+                }
+            }
+            // The removals of the previous nodes overlapping this one are merged into it, and the edits it contains are dropped:
+            // overlapping edits would be skipped when applied
+            while let Some(index) = self.edits.iter().position(|edit| edit.range.start < range.end && range.start < edit.range.end
+                && (edit.replacement.is_empty() || (range.start <= edit.range.start && edit.range.end <= range.end))) {
+                let edit = self.edits.remove(index);
+                range = range.start.min(edit.range.start)..range.end.max(edit.range.end);
+            }
+            self.push_edit(range, String::new());
+        }
+        is_kept
+    }
+
+    /// The source text of tokens when they come from the source, or else their formatting
+    fn tokens_text(&self, tokens: &TokenStream) -> String {
+        let ranges: Vec<Range<usize>> = tokens.clone().into_iter().map(|token| token.span().byte_range()).collect();
+        match (ranges.first(), ranges.last()) {
+            (Some(first), Some(last)) if ranges.iter().all(|range| range.end > range.start) && first.start < last.end => {
+                self.source[first.start + self.offset..last.end + self.offset].to_string()
+            }
+            _ => tokens.to_string(),
+        }
+    }
+
+    /// Rewrite the `$crate` metavariables of a macro definition body
+    fn rewrite_dollar_crate(&mut self, tokens: TokenStream) {
+        let mut previous_is_dollar = false;
+        for token in tokens {
+            match &token {
+                TokenTree::Group(group) => self.rewrite_dollar_crate(group.stream()),
+                TokenTree::Ident(ident) if previous_is_dollar && ident == "crate" && self.crate_module_path != "crate" => {
+                    // `$crate` is now the root of the merged crate, the crate root module has to be appended
+                    let range = self.range(ident.span());
+                    self.push_edit(range, self.crate_module_path.to_string());
+                }
+                _ => {}
+            }
+            previous_is_dollar = matches!(&token, TokenTree::Punct(punct) if punct.as_char() == '$');
+        }
+    }
+
+    /// Rewrite the paths of the tokens of a macro, that are not parsed: an identifier followed by `::` starts a path,
+    /// unless it comes after `::`, `.` or `$`
+    fn rewrite_macro_paths(&mut self, tokens: TokenStream) {
+        let tokens: Vec<TokenTree> = tokens.into_iter().collect();
+        for (index, token) in tokens.iter().enumerate() {
+            match token {
+                TokenTree::Group(group) => self.rewrite_macro_paths(group.stream()),
+                TokenTree::Ident(ident) => {
+                    let is_followed_by_path_separator = matches!(tokens.get(index + 1), Some(TokenTree::Punct(punct)) if punct.as_char() == ':' && punct.spacing() == Spacing::Joint)
+                        && matches!(tokens.get(index + 2), Some(TokenTree::Punct(punct)) if punct.as_char() == ':');
+                    let is_inner_segment = index.checked_sub(1).and_then(|previous| tokens.get(previous))
+                        .is_some_and(|previous| matches!(previous, TokenTree::Punct(punct) if matches!(punct.as_char(), ':' | '.' | '$')));
+                    if is_followed_by_path_separator && !is_inner_segment {
+                        self.rewrite_root_segment(ident, None);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for EditCollector<'_> {
+    // ##### cfg evaluation and test items removal
+    fn visit_item(&mut self, item: &'ast Item) {
+        // The item is configured before being visited, so that the files of removed modules are not even looked up
+        if self.configure(item_attributes(item), item.span(), false) {
+            visit::visit_item(self, item);
+        }
+    }
+
+    fn visit_impl_item(&mut self, impl_item: &'ast ImplItem) {
+        if self.configure(impl_item_attributes(impl_item), impl_item.span(), false) {
+            visit::visit_impl_item(self, impl_item);
+        }
+    }
+
+    fn visit_trait_item(&mut self, trait_item: &'ast TraitItem) {
+        if self.configure(trait_item_attributes(trait_item), trait_item.span(), false) {
+            visit::visit_trait_item(self, trait_item);
+        }
+    }
+
+    fn visit_field(&mut self, field: &'ast Field) {
+        if self.configure(&field.attrs, field.span(), true) {
+            visit::visit_field(self, field);
+        }
+    }
+
+    fn visit_variant(&mut self, variant: &'ast Variant) {
+        if self.configure(&variant.attrs, variant.span(), true) {
+            visit::visit_variant(self, variant);
+        }
+    }
+
+    fn visit_arm(&mut self, arm: &'ast Arm) {
+        if self.configure(&arm.attrs, arm.span(), true) {
+            visit::visit_arm(self, arm);
+        }
+    }
+
+    fn visit_field_value(&mut self, field_value: &'ast FieldValue) {
+        if self.configure(&field_value.attrs, field_value.span(), true) {
+            visit::visit_field_value(self, field_value);
+        }
+    }
+
+    fn visit_field_pat(&mut self, field_pat: &'ast FieldPat) {
+        if self.configure(&field_pat.attrs, field_pat.span(), true) {
+            visit::visit_field_pat(self, field_pat);
+        }
+    }
+
+    fn visit_fn_arg(&mut self, fn_arg: &'ast FnArg) {
+        let attributes = match fn_arg {
+            FnArg::Receiver(receiver) => &receiver.attrs,
+            FnArg::Typed(pat_type) => &pat_type.attrs,
+        };
+        if self.configure(attributes, fn_arg.span(), true) {
+            visit::visit_fn_arg(self, fn_arg);
+        }
+    }
+
+    fn visit_generic_param(&mut self, generic_param: &'ast GenericParam) {
+        let attributes = match generic_param {
+            GenericParam::Lifetime(lifetime_param) => &lifetime_param.attrs,
+            GenericParam::Type(type_param) => &type_param.attrs,
+            GenericParam::Const(const_param) => &const_param.attrs,
+        };
+        if self.configure(attributes, generic_param.span(), true) {
+            visit::visit_generic_param(self, generic_param);
+        }
+    }
+
+    fn visit_expr(&mut self, expr: &'ast Expr) {
+        // Expressions can only be removed from lists (array elements, call arguments...)
+        if self.configure(expr_attributes(expr), expr.span(), true) {
+            visit::visit_expr(self, expr);
+        }
+    }
+
+    fn visit_stmt(&mut self, stmt: &'ast Stmt) {
+        if let Stmt::Expr(expr, _) = stmt {
+            // The attributes of an expression statement apply to the whole statement, its semicolon included
+            if self.configure(expr_attributes(expr), stmt.span(), false) {
+                visit::visit_expr(self, expr);
             }
+            return;
+        }
+        let attributes: &[Attribute] = match stmt {
+            Stmt::Local(local) => &local.attrs,
+            Stmt::Macro(stmt_macro) => &stmt_macro.attrs,
+            _ => &[],
+        };
+        if self.configure(attributes, stmt.span(), false) {
+            visit::visit_stmt(self, stmt);
+        }
+    }
+
+    // ##### mod declaration rewrite
+    fn visit_item_mod(&mut self, item_mod: &'ast ItemMod) {
+        let module_name = item_mod.ident.to_string();
+        let path_attribute = item_mod.attrs.iter().find_map(path_attribute);
+        match (&item_mod.content, &item_mod.semi) {
+            (None, Some(semi)) => {
+                // The module is declared in its own file, expand it in place of the declaration (attributes are kept)
+                for attribute in &item_mod.attrs {
+                    self.visit_attribute(attribute);
+                }
+                let start = match &item_mod.vis {
+                    Visibility::Inherited => item_mod.mod_token.span,
+                    visibility => visibility.span(),
+                };
+                let range = self.range(start).start..self.range(semi.span).end;
+
+                // Inject the module content recursively
+                let module_content = match &path_attribute {
+                    Some((attribute, path)) => {
+                        // The file is given explicitly, and owns its folder like a mod.rs file would
+                        let module_file_path = self.path_attribute_base().join(path);
+                        debug!("Module {} declared with a path attribute: {:?}", module_name, module_file_path);
+                        if !module_file_path.is_file() {
+                            self.error.get_or_insert(MergeError::ModuleNotFound {
+                                module_name,
+                                declaring_file: self.file_path.to_path_buf(),
+                                line: item_mod.ident.span().start().line,
+                                path_attribute: Some(path.clone()),
+                                expected_paths: vec![module_file_path],
+                            });
+                            return;
+                        }
+                        let module_dir = module_file_path.parent().map(Path::to_path_buf).unwrap_or_default();
+
+                        // The attribute is meaningless once the module is expanded
+                        let attribute_range = expand_to_lines(self.source, self.range(attribute.span()));
+                        self.push_edit(attribute_range, String::new());
+
+                        self.merge.inject_modules(&module_file_path, module_dir, self.crate_module_path, self.crate_data)
+                    }
+                    None => {
+                        // Be it `name.rs` or `name/mod.rs`, the nested module files are located in the `name` folder
+                        let module_dir = self.module_dir.join(&module_name);
+                        self.resolve_module_file(&module_name, item_mod.ident.span().start().line)
+                            .and_then(|module_file_path| self.merge.inject_modules(&module_file_path, module_dir, self.crate_module_path, self.crate_data))
+                    }
+                };
+                let module_content = match module_content {
+                    Ok(module_content) => module_content,
+                    Err(error) => {
+                        self.error.get_or_insert(error);
+                        return;
+                    }
+                };
+                // The module content is preceded by a generated line, and followed by an empty line and a generated line
+                let mut origins = vec![None];
+                origins.extend(module_content.origins);
+                self.expanded_modules.insert(range.start, origins);
+                self.push_edit(range, format!("pub mod {} {{\n{}\n}}", module_name, module_content.text));
+            }
+            _ => {
+                // Inline module, its nested module files are located in a folder named as the module, or given by its path attribute
+                let module_dir = match &path_attribute {
+                    Some((_, path)) => self.path_attribute_base().join(path),
+                    None => self.module_dir.join(&module_name),
+                };
+                let parent_module_dir = std::mem::replace(&mut self.module_dir, module_dir);
+                self.inline_depth += 1;
+                visit::visit_item_mod(self, item_mod);
+                self.inline_depth -= 1;
+                self.module_dir = parent_module_dir;
+            }
+        }
+    }
+
+    // ##### use declaration rewrite
+    fn visit_item_use(&mut self, item_use: &'ast ItemUse) {
+        debug!("found use declaration: {}", &self.source[self.range(item_use.span())]);
+        for attribute in &item_use.attrs {
+            self.visit_attribute(attribute);
+        }
+        self.rewrite_use_tree(&item_use.tree, item_use.leading_colon.as_ref());
+    }
+
+    // ##### path rewrite (expressions, types, patterns, ...)
+    fn visit_path(&mut self, path: &'ast syn::Path) {
+        // Single segment paths cannot refer to an item of another crate
+        if path.segments.len() > 1 {
+            self.rewrite_root_segment(&path.segments[0].ident, path.leading_colon.as_ref());
+        }
+        visit::visit_path(self, path);
+    }
+
+    fn visit_vis_restricted(&mut self, vis_restricted: &'ast VisRestricted) {
+        // `pub(crate)`, `pub(self)` and `pub(super)` are keywords, only `pub(in path)` holds a path to rewrite
+        if vis_restricted.in_token.is_some() {
+            self.rewrite_root_segment(&vis_restricted.path.segments[0].ident, vis_restricted.path.leading_colon.as_ref());
+        }
+    }
+
+    // ##### doc comments removal
+    fn visit_attribute(&mut self, attribute: &'ast Attribute) {
+        if self.merge.opts.remove_doc_comments && is_doc_attribute(attribute) {
+            let range = expand_to_lines(self.source, self.range(attribute.span()));
+            self.push_edit(range, String::new());
+        }
+        visit::visit_attribute(self, attribute);
+    }
+
+    fn visit_macro(&mut self, mac: &'ast Macro) {
+        // The elements of `vec!` are expressions, visited as such so that their attributes are evaluated too
+        let elements = match mac.path.is_ident("vec") {
+            true => Punctuated::<Expr, Token![,]>::parse_terminated.parse2(mac.tokens.clone()).ok(),
+            false => None,
+        };
+        match elements {
+            Some(elements) => elements.iter().for_each(|element| self.visit_expr(element)),
+            None => self.rewrite_macro_paths(mac.tokens.clone()),
+        }
+        visit::visit_macro(self, mac);
+    }
+
+    fn visit_item_macro(&mut self, item_macro: &'ast ItemMacro) {
+        if item_macro.mac.path.is_ident("macro_rules") {
+            self.rewrite_dollar_crate(item_macro.mac.tokens.clone());
+        }
+        visit::visit_item_macro(self, item_macro);
+    }
+
+    // ##### eprint! and eprintln! removal
+    fn visit_stmt_macro(&mut self, stmt_macro: &'ast StmtMacro) {
+        if self.merge.opts.remove_error_output && is_eprint_macro(&stmt_macro.mac) {
+            // Remove the whole statement, and its line if nothing else is on it
+            let range = self.range(stmt_macro.span());
+            self.push_edit(expand_to_lines(self.source, range), String::new());
+        } else {
+            visit::visit_stmt_macro(self, stmt_macro);
+        }
+    }
+
+    fn visit_expr_macro(&mut self, expr_macro: &'ast ExprMacro) {
+        if expr_macro.mac.path.is_ident("cfg") {
+            // `cfg!(...)` evaluates to a boolean literal when its predicate is known
+            let evaluator = self.cfg_evaluator();
+            let range = self.range(expr_macro.span());
+            match evaluator.evaluate_tokens(expr_macro.mac.tokens.clone()) {
+                Cfg::True => self.push_edit(range, String::from("true")),
+                Cfg::False => self.push_edit(range, String::from("false")),
+                Cfg::Unknown(predicate) => {
+                    let predicate = self.tokens_text(&predicate);
+                    if predicate != self.tokens_text(&expr_macro.mac.tokens) {
+                        self.push_edit(range, format!("cfg!({})", predicate));
+                    }
+                }
+            }
+        } else if self.merge.opts.remove_error_output && is_eprint_macro(&expr_macro.mac) {
+            // In expression position, the call is replaced by the value it evaluates to
+            let range = self.range(expr_macro.span());
+            self.push_edit(range, String::from("()"));
+        } else {
+            visit::visit_expr_macro(self, expr_macro);
+        }
+    }
+}
+
+/// Extract the value of a `#[path = "..."]` attribute
+fn path_attribute(attribute: &Attribute) -> Option<(&Attribute, String)> {
+    match &attribute.meta {
+        Meta::NameValue(MetaNameValue { path, value: Expr::Lit(ExprLit { lit: Lit::Str(value), .. }), .. }) if path.is_ident("path") => {
+            Some((attribute, value.value()))
+        }
+        _ => None,
+    }
+}
+
+fn item_attributes(item: &Item) -> &[Attribute] {
+    match item {
+        Item::Const(item) => &item.attrs,
+        Item::Enum(item) => &item.attrs,
+        Item::ExternCrate(item) => &item.attrs,
+        Item::Fn(item) => &item.attrs,
+        Item::ForeignMod(item) => &item.attrs,
+        Item::Impl(item) => &item.attrs,
+        Item::Macro(item) => &item.attrs,
+        Item::Mod(item) => &item.attrs,
+        Item::Static(item) => &item.attrs,
+        Item::Struct(item) => &item.attrs,
+        Item::Trait(item) => &item.attrs,
+        Item::TraitAlias(item) => &item.attrs,
+        Item::Type(item) => &item.attrs,
+        Item::Union(item) => &item.attrs,
+        Item::Use(item) => &item.attrs,
+        _ => &[],
+    }
+}
+
+fn expr_attributes(expr: &Expr) -> &[Attribute] {
+    match expr {
+        Expr::Array(expr) => &expr.attrs,
+        Expr::Assign(expr) => &expr.attrs,
+        Expr::Async(expr) => &expr.attrs,
+        Expr::Await(expr) => &expr.attrs,
+        Expr::Binary(expr) => &expr.attrs,
+        Expr::Block(expr) => &expr.attrs,
+        Expr::Break(expr) => &expr.attrs,
+        Expr::Call(expr) => &expr.attrs,
+        Expr::Cast(expr) => &expr.attrs,
+        Expr::Closure(expr) => &expr.attrs,
+        Expr::Const(expr) => &expr.attrs,
+        Expr::Continue(expr) => &expr.attrs,
+        Expr::Field(expr) => &expr.attrs,
+        Expr::ForLoop(expr) => &expr.attrs,
+        Expr::Group(expr) => &expr.attrs,
+        Expr::If(expr) => &expr.attrs,
+        Expr::Index(expr) => &expr.attrs,
+        Expr::Infer(expr) => &expr.attrs,
+        Expr::Let(expr) => &expr.attrs,
+        Expr::Lit(expr) => &expr.attrs,
+        Expr::Loop(expr) => &expr.attrs,
+        Expr::Macro(expr) => &expr.attrs,
+        Expr::Match(expr) => &expr.attrs,
+        Expr::MethodCall(expr) => &expr.attrs,
+        Expr::Paren(expr) => &expr.attrs,
+        Expr::Path(expr) => &expr.attrs,
+        Expr::Range(expr) => &expr.attrs,
+        Expr::RawAddr(expr) => &expr.attrs,
+        Expr::Reference(expr) => &expr.attrs,
+        Expr::Repeat(expr) => &expr.attrs,
+        Expr::Return(expr) => &expr.attrs,
+        Expr::Struct(expr) => &expr.attrs,
+        Expr::Try(expr) => &expr.attrs,
+        Expr::TryBlock(expr) => &expr.attrs,
+        Expr::Tuple(expr) => &expr.attrs,
+        Expr::Unary(expr) => &expr.attrs,
+        Expr::Unsafe(expr) => &expr.attrs,
+        Expr::While(expr) => &expr.attrs,
+        Expr::Yield(expr) => &expr.attrs,
+        _ => &[],
+    }
+}
+
+fn impl_item_attributes(impl_item: &ImplItem) -> &[Attribute] {
+    match impl_item {
+        ImplItem::Const(item) => &item.attrs,
+        ImplItem::Fn(item) => &item.attrs,
+        ImplItem::Type(item) => &item.attrs,
+        ImplItem::Macro(item) => &item.attrs,
+        _ => &[],
+    }
+}
+
+fn trait_item_attributes(trait_item: &TraitItem) -> &[Attribute] {
+    match trait_item {
+        TraitItem::Const(item) => &item.attrs,
+        TraitItem::Fn(item) => &item.attrs,
+        TraitItem::Type(item) => &item.attrs,
+        TraitItem::Macro(item) => &item.attrs,
+        _ => &[],
+    }
+}
+
+/// The tokens of the predicate of a `cfg_attr(predicate, attributes...)` attribute
+fn predicate_tokens(tokens: &TokenStream) -> TokenStream {
+    tokens.clone().into_iter()
+        .take_while(|token| !matches!(token, TokenTree::Punct(punct) if punct.as_char() == ','))
+        .collect()
+}
+
+/// Doc comments are `#[doc = "..."]` attributes, while `#[doc(hidden)]` like attributes are kept
+fn is_doc_attribute(attribute: &Attribute) -> bool {
+    matches!(&attribute.meta, Meta::NameValue(name_value) if name_value.path.is_ident("doc"))
+}
+
+fn is_eprint_macro(mac: &Macro) -> bool {
+    mac.path.segments.last()
+        .is_some_and(|segment| EPRINT_MACROS.iter().any(|name| segment.ident == name))
+}
+
+/// Offset to add to the spans of a parsed source: a shebang line is stripped by syn before parsing, which shifts all the spans.
+/// The merged file starts with one when the shebang of a binary crate root is kept
+pub(crate) fn shebang_offset(syntax_tree: &syn::File) -> usize {
+    syntax_tree.shebang.as_ref().map_or(0, String::len)
+}
+
+/// Extend a byte range to the whole lines it spans, if nothing else than whitespace is on these lines
+pub(crate) fn expand_to_lines(source: &str, range: Range<usize>) -> Range<usize> {
+    let line_start = source[..range.start].rfind('\n').map_or(0, |index| index + 1);
+    let line_end = source[range.end..].find('\n').map_or(source.len(), |index| range.end + index + 1);
+    if source[line_start..range.start].trim().is_empty() && source[range.end..line_end].trim().is_empty() {
+        line_start..line_end
+    } else {
+        range
+    }
+}
+
+/// Extend a byte range to the whole lines it spans like `expand_to_lines` does, or else to the spaces following it on its line
+fn expand_to_lines_or_spaces(source: &str, range: Range<usize>) -> Range<usize> {
+    let lines = expand_to_lines(source, range.clone());
+    if lines != range {
+        return lines;
+    }
+    let rest = &source[range.end..];
+    range.start..range.end + rest.len() - rest.trim_start_matches([' ', '\t']).len()
+}
+
+/// Extend a range of whole lines to the blank lines following it, or else preceding it,
+/// so that removing it doesn't leave consecutive blank lines behind
+pub(crate) fn expand_to_blank_lines(source: &str, range: Range<usize>) -> Range<usize> {
+    if !(range.start == 0 || source[..range.start].ends_with('\n')) || !source[..range.end].ends_with('\n') {
+        return range;
+    }
+    let mut end = range.end;
+    while let Some(line_length) = source[end..].find('\n').map(|index| index + 1) {
+        if !source[end..end + line_length].trim().is_empty() {
+            break;
+        }
+        end += line_length;
+    }
+    if end > range.end {
+        return range.start..end;
+    }
+    let mut start = range.start;
+    while start > 0 {
+        let line_start = source[..start - 1].rfind('\n').map_or(0, |index| index + 1);
+        if !source[line_start..start].trim().is_empty() {
+            break;
+        }
+        start = line_start;
+    }
+    start..range.end
+}
+
+/// A piece of an edited source: a range of the source kept as is, or an edit replacing a range
+enum EditedPiece {
+    Kept(Range<usize>),
+    Replaced(SourceEdit),
+}
+
+/// Split a source into the pieces kept and replaced by non overlapping edits, in order.
+/// An edit starting in a range already replaced is skipped, as it is contained in it.
+fn edited_pieces(source: &str, mut edits: Vec<SourceEdit>) -> Vec<EditedPiece> {
+    edits.sort_by_key(|edit| edit.range.start);
+
+    let mut pieces = Vec::with_capacity(2 * edits.len() + 1);
+    let mut position = 0;
+    for edit in edits {
+        if edit.range.start < position {
+            debug_assert!(edit.range.end <= position, "edit {:?} overlaps a range already replaced", edit.range);
+            continue;
+        }
+        pieces.push(EditedPiece::Kept(position..edit.range.start));
+        position = edit.range.end;
+        pieces.push(EditedPiece::Replaced(edit));
+    }
+    pieces.push(EditedPiece::Kept(position..source.len()));
+    pieces
+}
+
+/// Apply non overlapping edits to a source
+pub(crate) fn apply_edits(source: &str, edits: Vec<SourceEdit>) -> String {
+    let mut output = String::with_capacity(source.len());
+    for piece in edited_pieces(source, edits) {
+        match piece {
+            EditedPiece::Kept(range) => output.push_str(&source[range]),
+            EditedPiece::Replaced(edit) => output.push_str(&edit.replacement),
         }
     }
+    output
 }
 
 
+/// Apply non overlapping edits to a module source, tracking where each line of the result comes from:
+/// the lines of the module file are located with `origin`, and the lines of the expanded modules come from their own file
+fn apply_module_edits(source: &str, edits: Vec<SourceEdit>, expanded_modules: &BTreeMap<usize, Vec<Option<LineOrigin>>>,
+                      origin: impl Fn(usize) -> LineOrigin) -> MergedSource {
+    let line_starts: Vec<usize> = std::iter::once(0).chain(source.match_indices('\n').map(|(index, _)| index + 1)).collect();
+    let line_number = |offset: usize| line_starts.partition_point(|line_start| *line_start <= offset);
+
+    let mut output = LineTracker::default();
+    for piece in edited_pieces(source, edits) {
+        match piece {
+            EditedPiece::Kept(range) => output.push(&source[range.clone()], |offset, _| Some(origin(line_number(range.start + offset)))),
+            EditedPiece::Replaced(edit) => match expanded_modules.get(&edit.range.start) {
+                Some(origins) => output.push(&edit.replacement, |_, index| origins.get(index).cloned().flatten()),
+                None => output.push(&edit.replacement, |_, _| Some(origin(line_number(edit.range.start)))),
+            },
+        }
+    }
+    output.source
+}
+
+/// Builds a merged source from chunks of text, assigning an origin to each line when it starts
+#[derive(Default)]
+struct LineTracker {
+    source: MergedSource,
+    /// Whether the text pushed so far ends in the middle of a line
+    continues_line: bool,
+}
+
+impl LineTracker {
+    /// Push a chunk of text, `line_origin` giving the origin of the lines starting in it from their offset in the chunk and their index
+    fn push(&mut self, chunk: &str, line_origin: impl Fn(usize, usize) -> Option<LineOrigin>) {
+        if chunk.is_empty() {
+            return;
+        }
+        let line_starts = std::iter::once(0)
+            .chain(chunk.match_indices('\n').map(|(index, _)| index + 1))
+            .filter(|line_start| *line_start < chunk.len());
+        for (index, line_start) in line_starts.enumerate() {
+            if line_start > 0 || !self.continues_line {
+                self.source.origins.push(line_origin(line_start, index));
+            }
+        }
+        self.source.text.push_str(chunk);
+        self.continues_line = !chunk.ends_with('\n');
+    }
+}
+
 /// Try to find the package root by detecting the Cargo.toml file
-pub fn detect_package_root() -> PathBuf {
-    let mut current_folder = std::env::current_dir().unwrap();
+pub fn detect_package_root() -> Result<PathBuf, MergeError> {
+    let search_start = current_dir()?;
+    let mut current_folder = search_start.clone();
     loop {
         // If we found the package root, return it
         let cargo_toml_path = current_folder.as_path().join(CARGO_TOML);
         if cargo_toml_path.exists() {
             debug!("Package root detected at: {:?}", current_folder);
-            return current_folder;
+            return Ok(current_folder);
         }
 
         // Else, go up
@@ -226,37 +1275,381 @@ pub fn detect_package_root() -> PathBuf {
     }
 
     // Error case, we did not find the package root
-    panic!("Rust package root not found.")
+    Err(MergeError::PackageRootNotFound { search_start })
 }
 
-fn load_cargo_toml(package_root_path: &PathBuf) -> CargoData {
-    let cargo_toml = fs::read_to_string(package_root_path.join(CARGO_TOML))
-        .expect("Could not read Cargo.toml content");
+/// Find the package root from the path of its folder or of its Cargo.toml file, relative to the current directory
+pub(crate) fn package_root(package_path: &Path) -> Result<PathBuf, MergeError> {
+    let package_path = current_dir()?.join(package_path);
+    let package_root_path = if package_path.is_file() {
+        package_path.parent().map(Path::to_path_buf).unwrap_or_default()
+    } else {
+        package_path
+    };
+    if !package_root_path.join(CARGO_TOML).is_file() {
+        return Err(MergeError::PackageRootNotFound { search_start: package_root_path });
+    }
+    Ok(package_root_path)
+}
 
-    let cargo_toml = cargo_toml.parse::<Value>()
-        .expect("Could not parse Cargo.toml content");
+/// The current directory, against which the paths given in the options are resolved
+fn current_dir() -> Result<PathBuf, MergeError> {
+    std::env::current_dir().map_err(MergeError::io("."))
+}
+
+/// Read the package metadata, and load the whole graph of the dependencies to merge.
+/// Development dependencies are loaded too if asked, as they are available to examples.
+/// Dependencies specific to a platform are loaded unless the configuration options tell the platform does not match.
+fn load_cargo_data(package_root_path: &Path, registry_sources_path: Option<&Path>, include_dev_dependencies: bool, feature_request: &FeatureRequest, cfg_options: &CfgOptions) -> Result<CargoData, MergeError> {
+    let (package, workspace_root_path) = load_package(&package_root_path.join(CARGO_TOML))?;
 
-    // Grab the package name
-    let mut package_name = cargo_toml["package"]["name"].to_string();
-    // Remove eventual quotes
-    package_name = package_name.replace("\"", "").replace("-", "_");
+    let package_name = package.name.replace("-", "_");
     debug!("Package name: {}", package_name);
 
-    // Grab external crate that are declared with a path
-    let mut external_crates = HashMap::new();
-    for (name, description) in cargo_toml["dependencies"].as_table().unwrap() {
-        if description.is_table() {
-            let description = description.as_table().unwrap();
-            if  description.contains_key("path") {
-                let mut crate_path = PathBuf::from(description["path"].as_str().unwrap());
-                crate_path.push("src/lib");
-                external_crates.insert(name.clone(), crate_path);
-            }
+    // In a workspace, the Cargo.lock file is located at the workspace root
+    let registry = match registry_sources_path {
+        Some(registry_sources_path) => Some(Registry {
+            sources_path: registry_sources_path.to_path_buf(),
+            cargo_lock_path: workspace_root_path.join(CARGO_LOCK),
+            locked_packages: load_locked_packages(&workspace_root_path.join(CARGO_LOCK))?,
+        }),
+        None => None,
+    };
+
+    for feature in &feature_request.features {
+        let is_optional_dependency = package.dependencies.iter().any(|dependency| dependency.optional && manifest_name(dependency) == feature);
+        if !package.features.contains_key(feature) && !is_optional_dependency {
+            warn!("Package {} does not have feature {}", package.name, feature);
         }
     }
+    let enabled_features = resolve_features(&package, feature_request);
+    debug!("Features enabled: {:?}", enabled_features.features);
+
+    let mut external_crates = BTreeMap::new();
+    let mut loaded_crates = BTreeMap::new();
+    let dependencies = load_dependencies(&package, &enabled_features, cfg_options, registry.as_ref(), include_dev_dependencies, &mut external_crates, &mut loaded_crates)?;
 
-    CargoData {
+    // The targets can have custom names and paths declared in the manifest ([lib] and [[bin]] sections),
+    // or be discovered by cargo (src/lib.rs, src/main.rs, src/bin/*.rs)
+    let lib_crate = package.targets.iter()
+        .find(|target| target.is_lib())
+        .map(|lib_target| CrateData {
+            name: lib_target.name.replace("-", "_"),
+            crate_name: lib_target.name.replace("-", "_"),
+            root_path: lib_target.src_path.clone().into_std_path_buf(),
+            dependencies: dependencies.clone(),
+            features: enabled_features.features.clone(),
+        });
+    let bin_targets = package.targets.iter()
+        .filter(|target| target.is_bin())
+        .map(|bin_target| BinTarget {
+            name: bin_target.name.clone(),
+            root_path: bin_target.src_path.clone().into_std_path_buf(),
+        })
+        .collect();
+    let example_targets = package.targets.iter()
+        .filter(|target| target.is_example())
+        .map(|example_target| BinTarget {
+            name: example_target.name.clone(),
+            root_path: example_target.src_path.clone().into_std_path_buf(),
+        })
+        .collect();
+
+    Ok(CargoData {
         package_name,
-        external_crates
+        lib_crate,
+        bin_targets,
+        example_targets,
+        default_run: package.default_run.clone(),
+        dependencies,
+        features: enabled_features.features,
+        edition: package.edition.as_str().to_string(),
+        external_crates,
+    })
+}
+
+/// Select the binary target to merge: the one given in the options, the only one of the package,
+/// or else the one run by default by cargo
+pub(crate) fn select_bin_target<'a>(cargo_data: &'a CargoData, opts: &Opts) -> Result<Option<&'a BinTarget>, MergeError> {
+    if let Some(bin_name) = &opts.bin {
+        return find_target(&cargo_data.bin_targets, bin_name, "Binary").map(Some);
+    }
+    if let Some(example_name) = &opts.example {
+        return find_target(&cargo_data.example_targets, example_name, "Example").map(Some);
+    }
+
+    if let [bin_target] = cargo_data.bin_targets.as_slice() {
+        return Ok(Some(bin_target));
+    }
+
+    let default_bin_name = cargo_data.default_run.clone().unwrap_or_else(|| cargo_data.package_name.clone());
+    let bin_target = cargo_data.bin_targets.iter()
+        .find(|bin_target| bin_target.name.replace("-", "_") == default_bin_name.replace("-", "_"));
+    if bin_target.is_none() && !cargo_data.bin_targets.is_empty() {
+        warn!("Several binary targets found, none of them is merged (see the --bin option): {}", target_names(&cargo_data.bin_targets).join(", "));
+    }
+    Ok(bin_target)
+}
+
+fn find_target<'a>(targets: &'a [BinTarget], name: &str, kind: &str) -> Result<&'a BinTarget, MergeError> {
+    targets.iter()
+        .find(|target| target.name == name)
+        .ok_or_else(|| MergeError::TargetNotFound { kind: kind.to_string(), name: name.to_string(), available_targets: target_names(targets) })
+}
+
+fn target_names(targets: &[BinTarget]) -> Vec<String> {
+    targets.iter().map(|target| target.name.clone()).collect()
+}
+
+/// Read with `cargo metadata` the package described by a manifest, without resolving its dependencies.
+/// The root folder of the workspace the package belongs to is returned alongside.
+fn load_package(manifest_path: &Path) -> Result<(Package, PathBuf), MergeError> {
+    let metadata = MetadataCommand::new()
+        .manifest_path(manifest_path)
+        .no_deps()
+        .exec()
+        .map_err(|error| MergeError::ManifestParse { path: manifest_path.to_path_buf(), message: error.to_string() })?;
+
+    // In a workspace, all the members are listed
+    let manifest_path = manifest_path.canonicalize().map_err(MergeError::io(manifest_path))?;
+    let package = metadata.packages.into_iter()
+        .find(|package| package.manifest_path.as_std_path() == manifest_path)
+        .ok_or_else(|| MergeError::ManifestParse { path: manifest_path.clone(), message: String::from("package not found in the metadata") })?;
+
+    Ok((package, metadata.workspace_root.into_std_path_buf()))
+}
+
+/// Load the dependencies of a package, and recursively the dependencies of these dependencies into `external_crates`.
+/// Like cargo does, the features requested by all the packages depending on a crate, kept in `loaded_crates`, are unified.
+/// Returns the dependencies of the package, by the name they are used with in its source, mapped to the module they are merged into.
+fn load_dependencies(package: &Package, enabled_features: &EnabledFeatures, cfg_options: &CfgOptions, registry: Option<&Registry>, include_dev_dependencies: bool,
+                     external_crates: &mut BTreeMap<String, CrateData>, loaded_crates: &mut BTreeMap<String, LoadedCrate>) -> Result<BTreeMap<String, String>, MergeError> {
+    let mut dependencies = BTreeMap::new();
+    for dependency in &package.dependencies {
+        // Build dependencies, and unless asked development dependencies, are not part of the merged code
+        let is_merged = match dependency.kind {
+            DependencyKind::Normal => true,
+            DependencyKind::Development => include_dev_dependencies,
+            _ => false,
+        };
+        if !is_merged {
+            continue;
+        }
+        if dependency.optional && !enabled_features.optional_dependencies.contains(manifest_name(dependency)) {
+            debug!("Optional dependency {} is not enabled", dependency.name);
+            continue;
+        }
+        if let Some(platform) = dependency.target.as_ref().filter(|platform| !cfg_options.matches_platform(platform)) {
+            debug!("Dependency {} is not merged as it is specific to the platform {}", dependency.name, platform);
+            continue;
+        }
+
+        let dependency_manifest_path = if let Some(path) = &dependency.path {
+            path.as_std_path().join(CARGO_TOML)
+        }
+        else if dependency.source.as_deref().is_some_and(|source| source.starts_with("registry+") || source.starts_with("sparse+")) {
+            match registry {
+                Some(registry) => registry.find_crate(package, &dependency.name)?.join(CARGO_TOML),
+                None => {
+                    warn!("Dependency {} is not merged as it comes from a registry (see the --registry-dependencies option)", dependency.name);
+                    continue;
+                }
+            }
+        }
+        else {
+            warn!("Dependency {} is not merged as its source is not supported: {}", dependency.name, dependency.source.as_deref().unwrap_or_default());
+            continue;
+        };
+
+        let (dependency_package, _) = load_package(&dependency_manifest_path)?;
+        let lib_target = match dependency_package.targets.iter().find(|target| target.is_lib()) {
+            Some(lib_target) => lib_target,
+            None => {
+                warn!("Dependency {} is not merged as it has no library target", dependency.name);
+                continue;
+            }
+        };
+
+        // Each crate of the graph is merged once, even if several crates depend on it,
+        // and is loaded again only when new features are requested for it.
+        // When several versions of a crate are locked, each one is merged into its own module.
+        let crate_name = lib_target.name.replace("-", "_");
+        let version = dependency_package.version.to_string();
+        let module_name = match loaded_crates.get(&crate_name) {
+            Some(loaded_crate) if loaded_crate.version != version => format!("{}_{}", crate_name, version.replace(|c: char| !c.is_ascii_alphanumeric(), "_")),
+            _ => crate_name.clone(),
+        };
+        let mut feature_request = FeatureRequest {
+            features: dependency.features.iter().cloned().collect(),
+            default_features: dependency.uses_default_features,
+        };
+        feature_request.features.extend(enabled_features.dependency_features.get(manifest_name(dependency)).into_iter().flatten().cloned());
+        let previous_feature_request = loaded_crates.get(&module_name).map(|loaded_crate| &loaded_crate.feature_request);
+        if let Some(previous_feature_request) = previous_feature_request {
+            feature_request.features.extend(previous_feature_request.features.iter().cloned());
+            feature_request.default_features |= previous_feature_request.default_features;
+        }
+        if previous_feature_request != Some(&feature_request) {
+            debug!("Dependency {} merged as module {} from: {}", dependency.name, module_name, lib_target.src_path);
+            let crate_features = resolve_features(&dependency_package, &feature_request);
+            loaded_crates.insert(module_name.clone(), LoadedCrate { version, feature_request });
+            let crate_dependencies = load_dependencies(&dependency_package, &crate_features, cfg_options, registry, false, external_crates, loaded_crates)?;
+            external_crates.insert(module_name.clone(), CrateData {
+                name: module_name.clone(),
+                crate_name: crate_name.clone(),
+                root_path: lib_target.src_path.clone().into_std_path_buf(),
+                dependencies: crate_dependencies,
+                features: crate_features.features,
+            });
+        }
+
+        // A renamed dependency is used under its new name in the source
+        let used_name = dependency.rename.as_deref().unwrap_or(&crate_name).replace("-", "_");
+        dependencies.insert(used_name, module_name);
+    }
+    Ok(dependencies)
+}
+
+/// Name of a dependency in the manifest, under which it is referred to in the features
+fn manifest_name(dependency: &cargo_metadata::Dependency) -> &str {
+    dependency.rename.as_deref().unwrap_or(&dependency.name)
+}
+
+/// Resolve the features enabled for a package from the requested ones, following its `[features]` table
+fn resolve_features(package: &Package, feature_request: &FeatureRequest) -> EnabledFeatures {
+    let optional_dependencies: BTreeSet<&str> = package.dependencies.iter()
+        .filter(|dependency| dependency.optional)
+        .map(manifest_name)
+        .collect();
+
+    let mut enabled_features = EnabledFeatures::default();
+    let mut pending_features: Vec<String> = feature_request.features.iter().cloned().collect();
+    if feature_request.default_features && package.features.contains_key("default") {
+        pending_features.push(String::from("default"));
+    }
+    while let Some(feature) = pending_features.pop() {
+        if !enabled_features.features.insert(feature.clone()) {
+            continue;
+        }
+        // An optional dependency not referred to with `dep:` is also an implicit feature
+        if optional_dependencies.contains(feature.as_str()) && !package.features.contains_key(&feature) {
+            enabled_features.optional_dependencies.insert(feature.clone());
+        }
+        for value in package.features.get(&feature).into_iter().flatten() {
+            if let Some(dependency_name) = value.strip_prefix("dep:") {
+                enabled_features.optional_dependencies.insert(dependency_name.to_string());
+            } else if let Some((dependency_name, dependency_feature)) = value.split_once('/') {
+                // `name?/feature` doesn't enable the optional dependency, only its feature if it is enabled
+                let dependency_name = match dependency_name.strip_suffix('?') {
+                    Some(dependency_name) => dependency_name,
+                    None => {
+                        if optional_dependencies.contains(dependency_name) {
+                            enabled_features.optional_dependencies.insert(dependency_name.to_string());
+                        }
+                        dependency_name
+                    }
+                };
+                enabled_features.dependency_features.entry(dependency_name.to_string()).or_default().insert(dependency_feature.to_string());
+            } else {
+                pending_features.push(value.clone());
+            }
+        }
+    }
+    enabled_features
+}
+
+/// Sources of the registry dependencies, with the versions locked in Cargo.lock
+struct Registry {
+    sources_path: PathBuf,
+    cargo_lock_path: PathBuf,
+    locked_packages: Vec<LockedPackage>,
+}
+
+/// A package entry of Cargo.lock
+struct LockedPackage {
+    name: String,
+    version: String,
+    /// Whether the package comes from a registry or git, or is a local one
+    has_source: bool,
+    /// Dependencies listed as "name" or, when several versions of a package are locked, "name version"
+    dependencies: Vec<String>,
+}
+
+impl Registry {
+    /// Find the sources of the locked version of the registry dependency `name` of a package
+    fn find_crate(&self, package: &Package, name: &str) -> Result<PathBuf, MergeError> {
+        let package_version = package.version.to_string();
+        let locked_dependency = self.locked_packages.iter()
+            .find(|locked_package| locked_package.name == package.name && locked_package.version == package_version)
+            .and_then(|locked_package| locked_package.dependencies.iter().find(|dependency| dependency.split_whitespace().next() == Some(name)));
+
+        let version = locked_dependency
+            .and_then(|dependency| dependency.split_whitespace().nth(1))
+            .or_else(|| {
+                self.locked_packages.iter()
+                    .find(|locked_package| locked_package.name == name && locked_package.has_source)
+                    .map(|locked_package| locked_package.version.as_str())
+            })
+            .ok_or_else(|| MergeError::LockedVersionNotFound { name: name.to_string(), cargo_lock_path: self.cargo_lock_path.clone() })?;
+
+        let crate_path = find_registry_crate(&self.sources_path, name, version)?;
+        debug!("Registry dependency {} {} found at: {:?}", name, version, crate_path);
+        Ok(crate_path)
     }
 }
+
+/// Read the package entries of a Cargo.lock file
+fn load_locked_packages(cargo_lock_path: &Path) -> Result<Vec<LockedPackage>, MergeError> {
+    // Cargo.lock is required to merge registry dependencies
+    let cargo_lock = fs::read_to_string(cargo_lock_path).map_err(MergeError::io(cargo_lock_path))?;
+    let cargo_lock = cargo_lock.parse::<Value>()
+        .map_err(|error| MergeError::ManifestParse { path: cargo_lock_path.to_path_buf(), message: error.to_string() })?;
+
+    let packages = cargo_lock.get("package").and_then(Value::as_array).cloned().unwrap_or_default();
+    Ok(packages.iter()
+        .filter_map(|package| Some(LockedPackage {
+            name: package.get("name")?.as_str()?.to_string(),
+            version: package.get("version")?.as_str()?.to_string(),
+            has_source: package.get("source").is_some(),
+            dependencies: package.get("dependencies")
+                .and_then(Value::as_array)
+                .map(|dependencies| dependencies.iter().filter_map(Value::as_str).map(String::from).collect())
+                .unwrap_or_default(),
+        }))
+        .collect())
+}
+
+/// Find the sources of a registry crate, either in a cargo registry cache layout (`<registry>/<name>-<version>`)
+/// or in a vendored folder (`<name>-<version>` or `<name>`)
+fn find_registry_crate(registry_sources_path: &Path, name: &str, version: &str) -> Result<PathBuf, MergeError> {
+    let versioned_name = format!("{}-{}", name, version);
+    let mut possible_crate_paths = vec![
+        registry_sources_path.join(&versioned_name),
+        registry_sources_path.join(name),
+    ];
+    if let Ok(registries) = fs::read_dir(registry_sources_path) {
+        let mut registries: Vec<PathBuf> = registries.filter_map(|entry| entry.ok()).map(|entry| entry.path()).collect();
+        registries.sort();
+        possible_crate_paths.extend(registries.into_iter().map(|registry| registry.join(&versioned_name)));
+    }
+
+    possible_crate_paths.into_iter()
+        .find(|possible_crate_path| {
+            debug!("Looking up registry dependency in: {:?}", possible_crate_path);
+            possible_crate_path.join(CARGO_TOML).is_file()
+        })
+        .ok_or_else(|| MergeError::RegistrySourcesNotFound {
+            name: name.to_string(),
+            version: version.to_string(),
+            registry_sources_path: registry_sources_path.to_path_buf(),
+        })
+}
+
+/// Default folder holding the registry dependencies sources: the cargo registry cache
+pub(crate) fn default_registry_sources_path() -> Result<PathBuf, MergeError> {
+    let cargo_home = std::env::var_os("CARGO_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE")).map(|home| PathBuf::from(home).join(".cargo")))
+        .ok_or(MergeError::CargoHomeNotFound)?;
+    Ok(cargo_home.join(CARGO_REGISTRY_SOURCES))
+}
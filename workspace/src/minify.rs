@@ -0,0 +1,108 @@
+use std::str::FromStr;
+use log::warn;
+use proc_macro2::{Delimiter, Spacing, TokenStream, TokenTree};
+
+const OPERATOR_PREFIXES: [&str; 25] = [
+    "::", "->", "=>", "==", "!=", "<=", ">=", "&&", "||", "<<", ">>", "..", ".=", "<-",
+    "+=", "-=", "*=", "/=", "%=", "^=", "&=", "|=", "//", "/*", "*/",
+];
+
+/// Rewrite a merged file with as few characters as possible: comments, doc comments and whitespace are dropped,
+/// and tokens are only separated when they would otherwise merge
+pub(crate) fn minify(source: &str) -> String {
+    let tokens = match TokenStream::from_str(source) {
+        Ok(tokens) => tokens,
+        Err(error) => {
+            warn!("Unable to tokenize the merged file, it is not minified: {:?}", error);
+            return source.to_string();
+        }
+    };
+
+    let mut output = String::with_capacity(source.len() / 2);
+    let mut previous = None;
+    write_tokens(tokens, &mut output, &mut previous);
+    output.push('\n');
+    output
+}
+
+/// The kind of the last written token, which tells whether a separator is needed before the next one
+#[derive(Clone, Copy)]
+enum Previous {
+    Word,
+    Literal,
+    Punct(char, Spacing),
+    Delimiter,
+}
+
+fn write_tokens(tokens: TokenStream, output: &mut String, previous: &mut Option<Previous>) {
+    let mut tokens = tokens.into_iter().peekable();
+    while let Some(token) = tokens.next() {
+        // Doc comments are turned into `#[doc = "..."]` (or `#![doc = "..."]`) attributes by the tokenizer
+        if let TokenTree::Punct(punct) = &token {
+            if punct.as_char() == '#' {
+                let mut lookahead = tokens.clone();
+                if matches!(lookahead.peek(), Some(TokenTree::Punct(punct)) if punct.as_char() == '!') {
+                    lookahead.next();
+                }
+                if let Some(TokenTree::Group(group)) = lookahead.next() {
+                    if is_doc_attribute(group.delimiter(), group.stream()) {
+                        tokens = lookahead;
+                        continue;
+                    }
+                }
+            }
+        }
+
+        match token {
+            TokenTree::Group(group) => {
+                let (open, close) = match group.delimiter() {
+                    Delimiter::Parenthesis => ("(", ")"),
+                    Delimiter::Brace => ("{", "}"),
+                    Delimiter::Bracket => ("[", "]"),
+                    Delimiter::None => ("", ""),
+                };
+                output.push_str(open);
+                *previous = Some(Previous::Delimiter);
+                write_tokens(group.stream(), output, previous);
+                output.push_str(close);
+                *previous = Some(Previous::Delimiter);
+            }
+            TokenTree::Ident(ident) => {
+                // Identifiers are glued to previous words, and would prefix string literals (`b"..."`)
+                if matches!(previous, Some(Previous::Word) | Some(Previous::Literal)) {
+                    output.push(' ');
+                }
+                output.push_str(&ident.to_string());
+                *previous = Some(Previous::Word);
+            }
+            TokenTree::Literal(literal) => {
+                // A literal after a word could be read as a prefixed literal, or a word after it as a suffix
+                if matches!(previous, Some(Previous::Word) | Some(Previous::Literal)) {
+                    output.push(' ');
+                }
+                output.push_str(&literal.to_string());
+                *previous = Some(Previous::Literal);
+            }
+            TokenTree::Punct(punct) => {
+                // Operators that were apart in the source must stay apart, like `/ *` that would start a comment
+                if let Some(Previous::Punct(previous_char, Spacing::Alone)) = previous {
+                    if is_operator_prefix(*previous_char, punct.as_char()) {
+                        output.push(' ');
+                    }
+                }
+                output.push(punct.as_char());
+                *previous = Some(Previous::Punct(punct.as_char(), punct.spacing()));
+            }
+        }
+    }
+}
+
+/// Whether two punctuation characters would be read as (the beginning of) a single operator or comment
+fn is_operator_prefix(first: char, second: char) -> bool {
+    OPERATOR_PREFIXES.iter().any(|prefix| prefix.starts_with(first) && prefix.ends_with(second))
+}
+
+fn is_doc_attribute(delimiter: Delimiter, stream: TokenStream) -> bool {
+    delimiter == Delimiter::Bracket
+        && matches!(stream.into_iter().next(), Some(TokenTree::Ident(ident)) if ident == "doc")
+}
@@ -1,14 +1,176 @@
 use structopt::StructOpt;
 use serde_derive::Deserialize;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::fmt;
+use std::time::Duration;
 
 /// Represents the various options
-#[derive(Debug, StructOpt, Deserialize)]
+#[derive(Debug, Default, StructOpt, Deserialize)]
 #[structopt(name = "cargo merge", about = "Merges the source code of a crate into a single file")]
 pub struct Opts {
+    /// Path to the Cargo.toml of the package to merge, instead of looking it up from the current directory
+    #[structopt(long="manifest-path", parse(from_os_str))]
+    pub manifest_path: Option<PathBuf>,
     /// Remove all the usages of eprint! and eprintln! macros
     #[structopt(short = "s", long="silence-standard-error-output")]
     pub remove_error_output: bool,
+    /// Merge the dependencies coming from a registry (like crates.io), using their sources from the local cargo cache
+    #[structopt(short = "r", long="registry-dependencies")]
+    pub merge_registry_dependencies: bool,
+    /// Folder containing the registry dependencies sources, like a vendored folder (defaults to the cargo registry cache)
+    #[structopt(long="registry-sources", parse(from_os_str))]
+    pub registry_sources: Option<PathBuf>,
+    /// Merge the given binary target, into a file named after it
+    #[structopt(long="bin", conflicts_with_all=&["example", "all-bins"])]
+    pub bin: Option<String>,
+    /// Merge the given example target, into a file named after it
+    #[structopt(long="example", conflicts_with="all-bins")]
+    pub example: Option<String>,
+    /// Merge every binary target, each one into a file named after it
+    #[structopt(long="all-bins")]
+    pub all_bins: bool,
+    /// Write the merged file to the given path instead of the merge folder, or to the standard output with `-`
+    #[structopt(short = "o", long="output", parse(from_os_str), conflicts_with="all-bins")]
+    pub output: Option<PathBuf>,
+    /// Remove the functions, types, impls, modules and constants that can't be reached from the main function
+    #[structopt(long="tree-shake")]
+    pub tree_shake: bool,
+    /// Features of the package to enable, space or comma separated and repeatable, against which `#[cfg(feature = "...")]` attributes are evaluated
+    #[structopt(long="features", number_of_values=1, use_delimiter=true)]
+    pub features: Vec<String>,
+    /// Do not enable the default feature of the package
+    #[structopt(long="no-default-features")]
+    pub no_default_features: bool,
+    /// Configuration option set on the judge, as `name` or `name="value"`, against which `#[cfg(...)]` attributes are evaluated
+    #[structopt(long="cfg", number_of_values=1)]
+    pub cfg: Vec<String>,
+    /// Target profile of the judge (linux, windows or macos, on x86_64 in release mode), against which `#[cfg(...)]` attributes and platform specific dependencies are evaluated
+    #[structopt(long="target-profile")]
+    pub target_profile: Option<TargetProfile>,
+    /// Keep the items gated by `#[cfg(test)]` and the `#[test]` functions, that are removed by default
+    #[structopt(long="keep-tests")]
+    pub keep_tests: bool,
+    /// Remove the doc comments and `#[doc = "..."]` attributes, along with the examples they contain
+    #[structopt(long="strip-doc-comments")]
+    pub remove_doc_comments: bool,
+    /// Minify the merged file, removing comments, doc comments and whitespace
+    #[structopt(long="minify")]
+    pub minify: bool,
+    /// Fail if the merged file is larger than the given size, in bytes by default or in chars with a `chars` suffix (like `100000chars`)
+    #[structopt(long="max-size")]
+    pub max_size: Option<SizeLimit>,
+    /// Compile the merged file with rustc, reporting the errors at their location in the original sources
+    #[structopt(long="check")]
+    pub check: bool,
+    /// Rust edition used to check the merged file and to compile it for the sample tests, the edition of the package by default
+    #[structopt(long="edition")]
+    pub edition: Option<String>,
+    /// Directory of sample tests: the merged binary is run on each `*.in` file, and its output compared with the matching `*.out` file
+    #[structopt(long="test-samples", parse(from_os_str))]
+    pub test_samples: Option<PathBuf>,
+    /// Time in seconds after which the merged binary is stopped and the sample failed, 10 seconds by default
+    #[structopt(long="sample-timeout", parse(try_from_str = parse_sample_timeout))]
+    pub sample_timeout: Option<f64>,
+    /// Merge the package again each time one of its source files changes
+    #[structopt(short = "w", long="watch")]
+    pub watch: bool,
+    /// Copy the merged file to the given path after each merge, like a file shared with the clipboard or the submission tool
+    #[structopt(long="copy-to", parse(from_os_str))]
+    pub copy_to: Option<PathBuf>,
     /// Debug mode (for cargo-merge development purpose)
     #[structopt(short = "d", long="debug")]
     pub debug: bool,
+    #[structopt(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// Commands run instead of a merge
+#[derive(Debug, PartialEq, StructOpt, Deserialize)]
+pub enum Command {
+    /// Find the original file and line of a line of the merged file, using the source map written alongside it
+    Locate {
+        /// Line number in the merged file, starting at 1
+        line: usize,
+        /// Source map to read, the one of the merged file of the package (or of the selected target) by default
+        #[structopt(long="map", parse(from_os_str))]
+        map: Option<PathBuf>,
+    },
+}
+
+/// The platform the merged file is compiled on
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub enum TargetProfile {
+    Linux,
+    Windows,
+    Macos,
+}
+
+impl FromStr for TargetProfile {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "linux" => Ok(TargetProfile::Linux),
+            "windows" => Ok(TargetProfile::Windows),
+            "macos" => Ok(TargetProfile::Macos),
+            _ => Err(format!("Unknown target profile `{}`, expected `linux`, `windows` or `macos`", value)),
+        }
+    }
+}
+
+/// A maximum size of the merged file
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct SizeLimit {
+    pub size: usize,
+    pub unit: SizeUnit,
+}
+
+/// The unit a size is measured in: judges limit either the bytes or the chars of a submission
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub enum SizeUnit {
+    Bytes,
+    Chars,
+}
+
+impl SizeUnit {
+    /// Size of a text in this unit
+    pub fn measure(&self, text: &str) -> usize {
+        match self {
+            SizeUnit::Bytes => text.len(),
+            SizeUnit::Chars => text.chars().count(),
+        }
+    }
+}
+
+impl fmt::Display for SizeUnit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SizeUnit::Bytes => write!(f, "bytes"),
+            SizeUnit::Chars => write!(f, "chars"),
+        }
+    }
+}
+
+impl FromStr for SizeLimit {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let digits_end = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+        let unit = match &value[digits_end..] {
+            "" | "bytes" => SizeUnit::Bytes,
+            "chars" => SizeUnit::Chars,
+            unit => return Err(format!("Unknown size unit `{}`, expected `bytes` or `chars`", unit)),
+        };
+        let size = value[..digits_end].parse()
+            .map_err(|_| format!("Invalid size `{}`", value))?;
+        Ok(SizeLimit { size, unit })
+    }
+}
+
+/// Parse a number of seconds that can be converted to a duration: neither negative, infinite, NaN nor too big
+fn parse_sample_timeout(value: &str) -> Result<f64, String> {
+    value.parse().ok()
+        .filter(|seconds| Duration::try_from_secs_f64(*seconds).is_ok())
+        .ok_or_else(|| format!("Invalid sample timeout `{}`, expected a positive number of seconds", value))
 }
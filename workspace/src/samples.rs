@@ -0,0 +1,144 @@
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+use log::warn;
+use crate::check::{remove_temporary_dir, rustc, temporary_dir};
+use crate::error::{CompileError, MergeError};
+
+/// Outcome of running the merged binary on a sample
+pub(crate) struct SampleResult {
+    /// File name of the sample input
+    pub(crate) name: String,
+    /// Description of the first difference with the expected output, if any
+    pub(crate) mismatch: Option<String>,
+}
+
+/// Time given to the merged binary to run on a sample, when no timeout is set with `--sample-timeout`
+pub(crate) const DEFAULT_SAMPLE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Compile the merged file in release mode, and run it on every `*.in` file of the samples directory,
+/// comparing its standard output with the matching `*.out` file
+pub(crate) fn run_samples(merged_file_path: &Path, samples_path: &Path, edition: &str, timeout: Duration) -> Result<Vec<SampleResult>, MergeError> {
+    let mut inputs: Vec<PathBuf> = fs::read_dir(samples_path)
+        .and_then(|entries| entries.map(|entry| entry.map(|entry| entry.path())).collect())
+        .map_err(MergeError::io(samples_path))?;
+    inputs.retain(|path| path.extension().is_some_and(|extension| extension == "in"));
+    inputs.sort();
+
+    let out_dir = temporary_dir("samples")?;
+    let binary_path = out_dir.join("merged");
+    let rustc = rustc();
+    let output = Command::new(&rustc)
+        .args(["--edition", edition, "--crate-type", "bin", "-C", "opt-level=3", "-o"])
+        .arg(&binary_path)
+        .arg(merged_file_path)
+        .output()
+        .map_err(MergeError::io(&rustc))?;
+    if !output.status.success() {
+        remove_temporary_dir(&out_dir);
+        let errors = vec![CompileError {
+            message: String::from_utf8_lossy(&output.stderr).into_owned(),
+            location: merged_file_path.display().to_string(),
+        }];
+        return Err(MergeError::CompileErrors { path: merged_file_path.to_path_buf(), errors });
+    }
+
+    let mut results = Vec::new();
+    for input_path in inputs {
+        let expected_path = input_path.with_extension("out");
+        let expected = match fs::read_to_string(&expected_path) {
+            Ok(expected) => expected,
+            Err(_) => {
+                warn!("No expected output {:?} for the sample {:?}, it is skipped", expected_path, input_path);
+                continue;
+            }
+        };
+        let input = fs::read(&input_path).map_err(MergeError::io(&input_path))?;
+        results.push(SampleResult {
+            name: input_path.file_name().unwrap().to_string_lossy().into_owned(),
+            mismatch: run_sample(&binary_path, input, &expected, timeout).map_err(MergeError::io(&binary_path))?,
+        });
+    }
+    remove_temporary_dir(&out_dir);
+    Ok(results)
+}
+
+/// Run the binary with the given input, returning the first difference of its output with the expected one.
+/// A binary still running after the timeout is killed, and the sample fails.
+fn run_sample(binary_path: &Path, input: Vec<u8>, expected: &str, timeout: Duration) -> io::Result<Option<String>> {
+    let mut child = Command::new(binary_path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    // The pipes are written and read from their own threads, as the binary may not read its whole input
+    // before writing its output, and both can be larger than the pipe buffers
+    let mut stdin = child.stdin.take().expect("the standard input of the binary is piped");
+    let writer = thread::spawn(move || match stdin.write_all(&input) {
+        // A binary that stops before reading all its input closes the pipe, which is not an error in itself
+        Err(error) if error.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        result => result,
+    });
+    let stdout_reader = read_in_background(child.stdout.take().expect("the standard output of the binary is piped"));
+    let stderr_reader = read_in_background(child.stderr.take().expect("the standard error output of the binary is piped"));
+
+    // A timeout too long to be represented never expires
+    let deadline = Instant::now().checked_add(timeout);
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break Some(status);
+        }
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            child.kill()?;
+            child.wait()?;
+            break None;
+        }
+        thread::sleep(Duration::from_millis(10));
+    };
+    let write_result = writer.join().expect("the input writer thread does not panic");
+    let stdout = stdout_reader.join().expect("the output reader thread does not panic")?;
+    let stderr = stderr_reader.join().expect("the output reader thread does not panic")?;
+
+    let status = match status {
+        Some(status) => status,
+        None => return Ok(Some(format!("timed out after {:?}", timeout))),
+    };
+    if !status.success() {
+        let stderr = String::from_utf8_lossy(&stderr);
+        return Ok(Some(format!("{}: {}", status, stderr.lines().last().unwrap_or_default())));
+    }
+    write_result?;
+    Ok(diff(expected, &String::from_utf8_lossy(&stdout)))
+}
+
+/// Read a whole output of the binary from another thread
+fn read_in_background<R: Read + Send + 'static>(mut output: R) -> thread::JoinHandle<io::Result<Vec<u8>>> {
+    thread::spawn(move || {
+        let mut buffer = Vec::new();
+        output.read_to_end(&mut buffer)?;
+        Ok(buffer)
+    })
+}
+
+/// Compare outputs like most judges do: line by line, ignoring trailing whitespace and trailing empty lines
+fn diff(expected: &str, actual: &str) -> Option<String> {
+    let expected = output_lines(expected);
+    let actual = output_lines(actual);
+    (0..expected.len().max(actual.len()))
+        .find(|&index| expected.get(index) != actual.get(index))
+        .map(|index| {
+            let describe = |line: Option<&&str>| line.map_or(String::from("end of output"), |line| format!("`{}`", line));
+            format!("line {}: expected {}, got {}", index + 1, describe(expected.get(index)), describe(actual.get(index)))
+        })
+}
+
+fn output_lines(output: &str) -> Vec<&str> {
+    let mut lines: Vec<&str> = output.lines().map(str::trim_end).collect();
+    while lines.last() == Some(&"") {
+        lines.pop();
+    }
+    lines
+}
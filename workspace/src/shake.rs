@@ -0,0 +1,405 @@
+//! Dead code elimination on a merged file.
+//!
+//! Items are linked by name: an item is kept as soon as an identifier matching its name appears in an
+//! item that is kept, starting from `main`. This over-approximates the real reference graph (two items
+//! with the same name in different modules are kept together), which keeps the output compilable.
+
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+use log::warn;
+use proc_macro2::{Span, TokenStream, TokenTree};
+use quote::ToTokens;
+use syn::{ImplItem, Item, ItemImpl, ItemUse, TraitItem, Type, UseTree, Visibility};
+use syn::spanned::Spanned;
+use crate::merge::{apply_edits, expand_to_blank_lines, expand_to_lines, shebang_offset, SourceEdit};
+
+/// Remove the items of a merged file that can't be reached from its `main` function
+pub(crate) fn remove_unused_items(source: &str) -> String {
+    let syntax_tree = match syn::parse_file(source) {
+        Ok(syntax_tree) => syntax_tree,
+        Err(error) => {
+            warn!("Unable to parse the merged file, unused items are kept: {}", error);
+            return source.to_string();
+        }
+    };
+
+    let mut graph = ItemGraph {
+        offset: shebang_offset(&syntax_tree),
+        ..ItemGraph::default()
+    };
+    graph.collect_local_names(&syntax_tree.items);
+    graph.add_items(&syntax_tree.items, None);
+    if !graph.nodes.iter().any(|node| node.parent.is_none() && node.names.iter().any(|name| name == "main")) {
+        warn!("No main function found in the merged file, unused items are kept");
+        return source.to_string();
+    }
+    graph.propagate();
+
+    // Remove the unused items whose parent is kept, the others being removed along with their parent
+    let mut ranges: Vec<Range<usize>> = graph.nodes.iter()
+        .filter(|node| !node.kept && node.parent.is_none_or(|parent| graph.nodes[parent].kept))
+        .map(|node| expand_to_lines(source, node.range.clone()))
+        .collect();
+    ranges.sort_by_key(|range| range.start);
+
+    // Consecutive removed items, only separated by blank lines, are removed together
+    let mut merged_ranges: Vec<Range<usize>> = Vec::new();
+    for range in ranges {
+        match merged_ranges.last_mut() {
+            Some(last) if range.start <= last.end || source[last.end..range.start].trim().is_empty() => last.end = last.end.max(range.end),
+            _ => merged_ranges.push(range),
+        }
+    }
+    let edits = merged_ranges.into_iter()
+        .map(|range| SourceEdit {
+            range: expand_to_blank_lines(source, range),
+            replacement: String::new(),
+        })
+        .collect();
+    apply_edits(source, edits)
+}
+
+/// An item of the merged file
+struct Node {
+    kind: NodeKind,
+    /// Byte range of the item, attributes and doc comments included
+    range: Range<usize>,
+    /// Names the item can be referenced with
+    names: Vec<String>,
+    /// Identifiers used by the item, that are kept along with it
+    references: HashSet<String>,
+    /// Index of the module or impl containing the item
+    parent: Option<usize>,
+    kept: bool,
+}
+
+enum NodeKind {
+    /// Always kept: `main`, item macros, extern blocks, external `use` declarations...
+    Root,
+    /// Kept when one of its names is referenced
+    Item,
+    /// Kept when its name or the name of one of its items is referenced, as its methods can be called without naming it
+    Trait { item_names: Vec<String> },
+    /// Kept when both its self type and its trait are kept, or are not items of the merged file
+    Impl { self_type: Option<String>, trait_name: Option<String> },
+    /// Item of an inherent impl, kept when the impl is kept and its name is referenced
+    ImplItem,
+    /// Local `use` declaration, kept when one of the names it imports is used, or when it imports a kept trait
+    Use { public: bool },
+    /// Kept when its name is referenced or when one of its items is kept
+    Module,
+}
+
+#[derive(Default)]
+struct ItemGraph {
+    offset: usize,
+    nodes: Vec<Node>,
+    /// Names of the modules of the merged file, that make a `use` declaration local
+    module_names: HashSet<String>,
+    /// Names of the types and traits of the merged file
+    type_names: HashSet<String>,
+    trait_names: HashSet<String>,
+}
+
+impl ItemGraph {
+    fn collect_local_names(&mut self, items: &[Item]) {
+        for item in items {
+            match item {
+                Item::Mod(item_mod) => {
+                    self.module_names.insert(item_mod.ident.to_string());
+                    if let Some((_, items)) = &item_mod.content {
+                        self.collect_local_names(items);
+                    }
+                }
+                Item::Struct(item) => { self.type_names.insert(item.ident.to_string()); }
+                Item::Enum(item) => { self.type_names.insert(item.ident.to_string()); }
+                Item::Union(item) => { self.type_names.insert(item.ident.to_string()); }
+                Item::Type(item) => { self.type_names.insert(item.ident.to_string()); }
+                Item::Trait(item) => {
+                    self.type_names.insert(item.ident.to_string());
+                    self.trait_names.insert(item.ident.to_string());
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn add_items(&mut self, items: &[Item], parent: Option<usize>) {
+        for item in items {
+            let range = self.range(item.span());
+            match item {
+                Item::Fn(item_fn) => {
+                    let name = item_fn.sig.ident.to_string();
+                    let kind = if parent.is_none() && name == "main" { NodeKind::Root } else { NodeKind::Item };
+                    self.push(kind, range, vec![name], identifiers(item), parent);
+                }
+                Item::Const(item_const) if item_const.ident == "_" => {
+                    self.push(NodeKind::Root, range, Vec::new(), identifiers(item), parent);
+                }
+                Item::Const(syn::ItemConst { ident, .. })
+                | Item::Static(syn::ItemStatic { ident, .. })
+                | Item::Struct(syn::ItemStruct { ident, .. })
+                | Item::Enum(syn::ItemEnum { ident, .. })
+                | Item::Union(syn::ItemUnion { ident, .. })
+                | Item::Type(syn::ItemType { ident, .. })
+                | Item::TraitAlias(syn::ItemTraitAlias { ident, .. })
+                | Item::Macro(syn::ItemMacro { ident: Some(ident), .. }) => {
+                    self.push(NodeKind::Item, range, vec![ident.to_string()], identifiers(item), parent);
+                }
+                Item::Trait(item_trait) => {
+                    let item_names = item_trait.items.iter()
+                        .filter_map(|trait_item| match trait_item {
+                            TraitItem::Fn(item) => Some(item.sig.ident.to_string()),
+                            TraitItem::Const(item) => Some(item.ident.to_string()),
+                            TraitItem::Type(item) => Some(item.ident.to_string()),
+                            _ => None,
+                        })
+                        .collect();
+                    self.push(NodeKind::Trait { item_names }, range, vec![item_trait.ident.to_string()], identifiers(item), parent);
+                }
+                Item::Impl(item_impl) => self.add_impl(item_impl, range, parent),
+                Item::Mod(item_mod) => match &item_mod.content {
+                    Some((_, items)) => {
+                        let mut references = HashSet::new();
+                        for attribute in &item_mod.attrs {
+                            collect_identifiers(attribute.to_token_stream(), &mut references);
+                        }
+                        let index = self.push(NodeKind::Module, range, vec![item_mod.ident.to_string()], references, parent);
+                        self.add_items(items, Some(index));
+                    }
+                    None => { self.push(NodeKind::Root, range, Vec::new(), identifiers(item), parent); }
+                },
+                Item::Use(item_use) => {
+                    let (kind, names) = match self.imported_names(item_use) {
+                        Some(names) => (NodeKind::Use { public: !matches!(item_use.vis, Visibility::Inherited) }, names),
+                        None => (NodeKind::Root, Vec::new()),
+                    };
+                    self.push(kind, range, names, identifiers(item), parent);
+                }
+                // Items defining names that are not known, such as macro invocations, are kept
+                _ => { self.push(NodeKind::Root, range, Vec::new(), identifiers(item), parent); }
+            }
+        }
+    }
+
+    fn add_impl(&mut self, item_impl: &ItemImpl, range: Range<usize>, parent: Option<usize>) {
+        let kind = NodeKind::Impl {
+            self_type: type_name(&item_impl.self_ty),
+            trait_name: item_impl.trait_.as_ref()
+                .and_then(|(_, path, _)| path.segments.last())
+                .map(|segment| segment.ident.to_string()),
+        };
+
+        // All the items of a trait impl are required, while the items of an inherent impl are kept only when used
+        if item_impl.trait_.is_some() {
+            self.push(kind, range, Vec::new(), identifiers(item_impl), parent);
+            return;
+        }
+        let mut references = HashSet::new();
+        for attribute in &item_impl.attrs {
+            collect_identifiers(attribute.to_token_stream(), &mut references);
+        }
+        collect_identifiers(item_impl.generics.to_token_stream(), &mut references);
+        collect_identifiers(item_impl.generics.where_clause.to_token_stream(), &mut references);
+        collect_identifiers(item_impl.self_ty.to_token_stream(), &mut references);
+        let index = self.push(kind, range, Vec::new(), references, parent);
+
+        for impl_item in &item_impl.items {
+            let names = match impl_item {
+                ImplItem::Fn(item) => vec![item.sig.ident.to_string()],
+                ImplItem::Const(item) => vec![item.ident.to_string()],
+                ImplItem::Type(item) => vec![item.ident.to_string()],
+                _ => Vec::new(),
+            };
+            let range = self.range(impl_item.span());
+            self.push(NodeKind::ImplItem, range, names, identifiers(impl_item), Some(index));
+        }
+    }
+
+    /// Names imported by a local `use` declaration, or `None` if it has to be kept
+    fn imported_names(&self, item_use: &ItemUse) -> Option<Vec<String>> {
+        let mut names = Vec::new();
+        if self.collect_imported_names(&item_use.tree, None, &mut names) {
+            Some(names)
+        } else {
+            None
+        }
+    }
+
+    /// Returns false if the tree imports from an external crate, or contains globs or `_` imports, whose usage is unknown
+    fn collect_imported_names(&self, tree: &UseTree, parent: Option<&syn::Ident>, names: &mut Vec<String>) -> bool {
+        match tree {
+            UseTree::Path(use_path) => {
+                if parent.is_none() && !self.is_local_root(&use_path.ident) {
+                    return false;
+                }
+                self.collect_imported_names(&use_path.tree, Some(&use_path.ident), names)
+            }
+            UseTree::Name(use_name) => {
+                if parent.is_none() && !self.is_local_root(&use_name.ident) {
+                    return false;
+                }
+                match parent {
+                    Some(parent) if use_name.ident == "self" => names.push(parent.to_string()),
+                    _ => names.push(use_name.ident.to_string()),
+                }
+                true
+            }
+            UseTree::Rename(use_rename) => {
+                if use_rename.rename == "_" || (parent.is_none() && !self.is_local_root(&use_rename.ident)) {
+                    return false;
+                }
+                names.push(use_rename.rename.to_string());
+                true
+            }
+            UseTree::Glob(_) => false,
+            UseTree::Group(use_group) => use_group.items.iter()
+                .all(|tree| self.collect_imported_names(tree, parent, names)),
+        }
+    }
+
+    fn is_local_root(&self, ident: &syn::Ident) -> bool {
+        ident == "crate" || ident == "self" || ident == "super" || self.module_names.contains(&ident.to_string())
+    }
+
+    fn range(&self, span: Span) -> Range<usize> {
+        let range = span.byte_range();
+        range.start + self.offset..range.end + self.offset
+    }
+
+    fn push(&mut self, kind: NodeKind, range: Range<usize>, names: Vec<String>, references: HashSet<String>, parent: Option<usize>) -> usize {
+        self.nodes.push(Node { kind, range, names, references, parent, kept: false });
+        self.nodes.len() - 1
+    }
+
+    /// Mark the items reachable from the roots, until no more item is found
+    fn propagate(&mut self) {
+        let mut reachability = Reachability::default();
+        loop {
+            let mut changed = false;
+            for index in 0..self.nodes.len() {
+                if self.nodes[index].kept || !self.is_reachable(index, &reachability) {
+                    continue;
+                }
+                self.nodes[index].kept = true;
+                reachability.add(&self.nodes, index);
+                changed = true;
+            }
+            if !changed {
+                break;
+            }
+        }
+
+        // The modules containing kept items are kept too
+        for index in 0..self.nodes.len() {
+            if self.nodes[index].kept {
+                let mut parent = self.nodes[index].parent;
+                while let Some(parent_index) = parent {
+                    self.nodes[parent_index].kept = true;
+                    parent = self.nodes[parent_index].parent;
+                }
+            }
+        }
+    }
+
+    fn is_reachable(&self, index: usize, reachability: &Reachability) -> bool {
+        let node = &self.nodes[index];
+        let is_referenced = || node.names.iter().any(|name| reachability.referenced.contains(name));
+        let is_kept_trait = |name: &String| self.trait_names.contains(name) && reachability.kept_names.contains(name);
+        match &node.kind {
+            NodeKind::Root => true,
+            NodeKind::Item | NodeKind::Module => is_referenced(),
+            NodeKind::Trait { item_names } => is_referenced() || item_names.iter().any(|name| reachability.referenced.contains(name)),
+            NodeKind::Impl { self_type, trait_name } => {
+                let self_type_kept = match self_type {
+                    Some(name) if self.type_names.contains(name) => reachability.kept_names.contains(name),
+                    _ => true,
+                };
+                let trait_kept = match trait_name {
+                    Some(name) if self.trait_names.contains(name) => reachability.kept_names.contains(name),
+                    _ => true,
+                };
+                self_type_kept && trait_kept
+            }
+            NodeKind::ImplItem => {
+                node.parent.is_some_and(|parent| self.nodes[parent].kept) && (node.names.is_empty() || is_referenced())
+            }
+            // A private import is only visible from its module and the modules nested in it
+            NodeKind::Use { public: true } => is_referenced() || node.names.iter().any(is_kept_trait),
+            NodeKind::Use { public: false } => {
+                let scope_references = reachability.scope_references.get(&node.parent);
+                node.names.iter()
+                    .any(|name| scope_references.is_some_and(|references| references.contains(name)) || is_kept_trait(name))
+            }
+        }
+    }
+}
+
+/// The state of the reachability propagation
+#[derive(Default)]
+struct Reachability {
+    /// Identifiers used by the kept items
+    referenced: HashSet<String>,
+    /// Names of the kept items
+    kept_names: HashSet<String>,
+    /// Identifiers used by the kept items of each module (`None` being the root), nested modules included
+    scope_references: HashMap<Option<usize>, HashSet<String>>,
+}
+
+impl Reachability {
+    fn add(&mut self, nodes: &[Node], index: usize) {
+        let node = &nodes[index];
+        self.referenced.extend(node.references.iter().cloned());
+        self.kept_names.extend(node.names.iter().cloned());
+
+        let mut scope = node.parent;
+        loop {
+            self.scope_references.entry(scope).or_default().extend(node.references.iter().cloned());
+            match scope {
+                Some(parent_index) => scope = nodes[parent_index].parent,
+                None => break,
+            }
+        }
+    }
+}
+
+/// Name of the type an impl is for, without its path, generics or references
+fn type_name(ty: &Type) -> Option<String> {
+    match ty {
+        Type::Path(type_path) => type_path.path.segments.last().map(|segment| segment.ident.to_string()),
+        Type::Reference(reference) => type_name(&reference.elem),
+        Type::Paren(paren) => type_name(&paren.elem),
+        Type::Group(group) => type_name(&group.elem),
+        _ => None,
+    }
+}
+
+fn identifiers<T: ToTokens>(node: &T) -> HashSet<String> {
+    let mut identifiers = HashSet::new();
+    collect_identifiers(node.to_token_stream(), &mut identifiers);
+    identifiers
+}
+
+fn collect_identifiers(tokens: TokenStream, identifiers: &mut HashSet<String>) {
+    for token in tokens {
+        match token {
+            TokenTree::Group(group) => collect_identifiers(group.stream(), identifiers),
+            TokenTree::Ident(ident) => { identifiers.insert(ident.to_string()); }
+            TokenTree::Literal(literal) => collect_format_arguments(&literal.to_string(), identifiers),
+            TokenTree::Punct(_) => {}
+        }
+    }
+}
+
+/// Collect the identifiers captured by a format string, such as `{value}` in `println!("{value}")`
+fn collect_format_arguments(literal: &str, identifiers: &mut HashSet<String>) {
+    if !literal.ends_with('"') {
+        return;
+    }
+    for argument in literal.split('{').skip(1) {
+        let name: String = argument.chars().take_while(|c| c.is_alphanumeric() || *c == '_').collect();
+        if !name.is_empty() && !name.starts_with(|c: char| c.is_ascii_digit()) {
+            identifiers.insert(name);
+        }
+    }
+}
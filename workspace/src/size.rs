@@ -0,0 +1,75 @@
+use log::warn;
+use syn::Item;
+use syn::spanned::Spanned;
+use crate::merge::shebang_offset;
+use crate::opts::SizeUnit;
+
+/// Size of a module of the merged file, its nested modules excluded
+pub(crate) struct ModuleSize {
+    /// Path of the module, starting with the name of the crate it belongs to, empty for the root of the file
+    pub(crate) path: String,
+    pub(crate) size: usize,
+}
+
+/// Measure the size of every module of a merged file, largest first
+pub(crate) fn module_sizes(source: &str, unit: SizeUnit) -> Vec<ModuleSize> {
+    let syntax_tree = match syn::parse_file(source) {
+        Ok(syntax_tree) => syntax_tree,
+        Err(error) => {
+            warn!("Unable to parse the merged file, its size can't be broken down: {}", error);
+            return Vec::new();
+        }
+    };
+    let offset = shebang_offset(&syntax_tree);
+
+    let mut module_sizes = Vec::new();
+    let nested_size = collect_module_sizes(source, offset, &syntax_tree.items, "", unit, &mut module_sizes);
+    module_sizes.push(ModuleSize { path: String::new(), size: unit.measure(source) - nested_size });
+    module_sizes.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.path.cmp(&b.path)));
+    module_sizes
+}
+
+/// Collect the sizes of the modules found in `items`, returning their total size
+fn collect_module_sizes(source: &str, offset: usize, items: &[Item], parent_path: &str, unit: SizeUnit, module_sizes: &mut Vec<ModuleSize>) -> usize {
+    let mut total_size = 0;
+    for item in items {
+        if let Item::Mod(item_mod) = item {
+            if let Some((_, items)) = &item_mod.content {
+                let range = item_mod.span().byte_range();
+                let size = unit.measure(&source[range.start + offset..range.end + offset]);
+                let path = if parent_path.is_empty() {
+                    item_mod.ident.to_string()
+                } else {
+                    format!("{}::{}", parent_path, item_mod.ident)
+                };
+                let nested_size = collect_module_sizes(source, offset, items, &path, unit, module_sizes);
+                module_sizes.push(ModuleSize { path, size: size - nested_size });
+                total_size += size;
+            }
+        }
+    }
+    total_size
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "pub mod a {\npub mod b {\nfn f() {}\n}\nconst E: &str = \"é\";\n}\nfn main() {}\n";
+
+    fn sizes(source: &str, unit: SizeUnit) -> Vec<(String, usize)> {
+        module_sizes(source, unit).into_iter().map(|module_size| (module_size.path, module_size.size)).collect()
+    }
+
+    #[test]
+    fn nested_modules_excluded() {
+        assert_eq!(sizes(SOURCE, SizeUnit::Bytes), vec![(String::from("a"), 36), (String::from("a::b"), 23), (String::new(), 14)]);
+        assert_eq!(sizes(SOURCE, SizeUnit::Chars), vec![(String::from("a"), 35), (String::from("a::b"), 23), (String::new(), 14)]);
+    }
+
+    #[test]
+    fn shebang_counted_in_root() {
+        let source = format!("#!/usr/bin/env run-cargo-script\n{}", SOURCE);
+        assert_eq!(sizes(&source, SizeUnit::Bytes), vec![(String::new(), 46), (String::from("a"), 36), (String::from("a::b"), 23)]);
+    }
+}
@@ -0,0 +1,118 @@
+use std::fmt::Write;
+use std::path::PathBuf;
+use serde_derive::{Deserialize, Serialize};
+
+/// Where a line of the merged file comes from
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineOrigin {
+    /// Name of the crate the line belongs to
+    pub crate_name: String,
+    /// Source file of the crate
+    pub file_path: PathBuf,
+    /// Line number in the source file, starting at 1
+    pub line: usize,
+}
+
+/// Merged source code, along with the origin of each of its lines.
+/// Lines generated by the merge, such as the `pub mod name {` wrappers, have no origin.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct MergedSource {
+    pub(crate) text: String,
+    pub(crate) origins: Vec<Option<LineOrigin>>,
+}
+
+impl MergedSource {
+    pub(crate) fn push_line(&mut self, line: &str, origin: Option<LineOrigin>) {
+        writeln!(self.text, "{}", line).unwrap();
+        self.origins.push(origin);
+    }
+
+    pub(crate) fn append(&mut self, other: &MergedSource) {
+        self.text.push_str(&other.text);
+        self.origins.extend(other.origins.iter().cloned());
+    }
+
+    /// Origin of a line of the merged file, given by its number starting at 1
+    pub(crate) fn origin(&self, line: usize) -> Option<&LineOrigin> {
+        line.checked_sub(1).and_then(|index| self.origins.get(index)).and_then(Option::as_ref)
+    }
+
+    /// The origins of a text derived from this source by removing or rewriting some lines (tree shaking, minification...):
+    /// the lines of the new text are matched, in order, with the identical lines of this source
+    pub(crate) fn derive(&self, text: String) -> MergedSource {
+        let lines: Vec<&str> = self.text.lines().collect();
+        let mut position = 0;
+        let origins = text.lines()
+            .map(|line| {
+                let index = lines[position..].iter().position(|source_line| *source_line == line)?;
+                position += index + 1;
+                self.origins[position - 1].clone()
+            })
+            .collect();
+        MergedSource { text, origins }
+    }
+
+    /// Build the source map of this source, merging the consecutive lines that come from consecutive lines of the same file
+    pub(crate) fn source_map(&self, file_name: &str) -> SourceMap {
+        let mut ranges: Vec<LineRange> = Vec::new();
+        for (index, origin) in self.origins.iter().enumerate() {
+            let origin = match origin {
+                Some(origin) => origin,
+                None => continue,
+            };
+            let line = index + 1;
+            if let Some(range) = ranges.last_mut() {
+                if range.end + 1 == line && range.line + line - range.start == origin.line
+                    && range.crate_name == origin.crate_name && range.file == origin.file_path {
+                    range.end = line;
+                    continue;
+                }
+            }
+            ranges.push(LineRange {
+                start: line,
+                end: line,
+                crate_name: origin.crate_name.clone(),
+                file: origin.file_path.clone(),
+                line: origin.line,
+            });
+        }
+        SourceMap { file: file_name.to_string(), ranges }
+    }
+}
+
+/// Sidecar file telling where the lines of a merged file come from
+#[derive(Serialize, Deserialize)]
+pub(crate) struct SourceMap {
+    /// Name of the merged file
+    pub(crate) file: String,
+    /// Ranges of lines of the merged file coming from the original sources, in order
+    pub(crate) ranges: Vec<LineRange>,
+}
+
+/// Consecutive lines of the merged file, coming from consecutive lines of an original file
+#[derive(Serialize, Deserialize)]
+pub(crate) struct LineRange {
+    /// First line of the range in the merged file, starting at 1
+    pub(crate) start: usize,
+    /// Last line of the range in the merged file, included
+    pub(crate) end: usize,
+    #[serde(rename = "crate")]
+    pub(crate) crate_name: String,
+    pub(crate) file: PathBuf,
+    /// Line of the original file the range starts at
+    pub(crate) line: usize,
+}
+
+impl SourceMap {
+    /// Origin of a line of the merged file, given by its number starting at 1
+    pub(crate) fn locate(&self, line: usize) -> Option<LineOrigin> {
+        let index = self.ranges.partition_point(|range| range.end < line);
+        self.ranges.get(index)
+            .filter(|range| range.start <= line)
+            .map(|range| LineOrigin {
+                crate_name: range.crate_name.clone(),
+                file_path: range.file.clone(),
+                line: range.line + line - range.start,
+            })
+    }
+}
@@ -0,0 +1,93 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+/// Time between two checks of the watched files
+const POLL_INTERVAL: Duration = Duration::from_millis(300);
+
+/// Watches files for changes, by polling their modification time
+pub(crate) struct FileWatcher {
+    /// Modification time of the watched files, `None` when they can't be read
+    modification_times: BTreeMap<PathBuf, Option<SystemTime>>,
+}
+
+impl FileWatcher {
+    pub(crate) fn new(files: impl IntoIterator<Item = PathBuf>) -> FileWatcher {
+        FileWatcher {
+            modification_times: files.into_iter()
+                .map(|file| {
+                    let modification_time = modification_time(&file);
+                    (file, modification_time)
+                })
+                .collect(),
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.modification_times.len()
+    }
+
+    /// Block until some of the watched files are modified, created or removed, and return them
+    pub(crate) fn wait_for_changes(&self) -> Vec<PathBuf> {
+        loop {
+            thread::sleep(POLL_INTERVAL);
+            let changed_files: Vec<PathBuf> = self.modification_times.iter()
+                .filter(|(file, last_modification_time)| modification_time(file) != **last_modification_time)
+                .map(|(file, _)| file.clone())
+                .collect();
+            if !changed_files.is_empty() {
+                // Editors often write a file in several steps, let them finish
+                thread::sleep(POLL_INTERVAL);
+                return changed_files;
+            }
+        }
+    }
+}
+
+/// A folder and all the files and folders it contains, so that the files created in it are noticed too
+pub(crate) fn folder_files(folder: &Path) -> Vec<PathBuf> {
+    let mut files = vec![folder.to_path_buf()];
+    let mut entries: Vec<PathBuf> = fs::read_dir(folder).into_iter().flatten().filter_map(|entry| entry.ok()).map(|entry| entry.path()).collect();
+    entries.sort();
+    for entry in entries {
+        if entry.is_dir() {
+            files.extend(folder_files(&entry));
+        } else {
+            files.push(entry);
+        }
+    }
+    files
+}
+
+fn modification_time(file: &Path) -> Option<SystemTime> {
+    fs::metadata(file).and_then(|metadata| metadata.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::check::{remove_temporary_dir, temporary_dir};
+
+    #[test]
+    fn modified_and_created_files() {
+        let dir = temporary_dir("watch").unwrap();
+        let modified_file = dir.join("modified.rs");
+        let created_file = dir.join("created.rs");
+        let unchanged_file = dir.join("unchanged.rs");
+        fs::write(&modified_file, "").unwrap();
+        fs::write(&unchanged_file, "").unwrap();
+        let watcher = FileWatcher::new(vec![modified_file.clone(), created_file.clone(), unchanged_file.clone()]);
+        assert_eq!(watcher.len(), 3);
+
+        // The modification time is moved forward explicitly, as the file system may not be precise enough to tell the writes apart
+        let file = fs::File::options().write(true).open(&modified_file).unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(10)).unwrap();
+        fs::write(&created_file, "").unwrap();
+        assert_eq!(watcher.wait_for_changes(), vec![created_file.clone(), modified_file.clone()]);
+
+        assert_eq!(folder_files(&dir), vec![dir.clone(), created_file, modified_file, unchanged_file]);
+        remove_temporary_dir(&dir);
+    }
+}
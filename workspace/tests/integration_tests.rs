@@ -1,32 +1,44 @@
-use cargo_merge::merge::{Merge, detect_package_root};
-use cargo_merge::opts::Opts;
+use cargo_merge::{MergeBuilder, MergeError, Target};
+use cargo_merge::merge::Merge;
+use cargo_merge::opts::{Opts, SizeLimit, SizeUnit, TargetProfile};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::process::Command;
 
-static mut BASE_DIR: Option<PathBuf> = None;
+/// Options merging the package found at the given path, whatever the current directory
+fn package_opts(test_path: &Path) -> Opts {
+    Opts { manifest_path: Some(test_path.join("Cargo.toml")), ..Opts::default() }
+}
 
-unsafe fn reset_base_dir() {
-    if BASE_DIR.is_none() {
-        BASE_DIR = Some(std::env::current_dir().unwrap())
-    }
-    else {
-        let base_path = BASE_DIR.clone().unwrap();
-        std::env::set_current_dir(base_path).unwrap();
-    }
+/// Options merging a binary of the package, written to an output file named after it,
+/// so that the tests merging the package as a whole can run in parallel
+fn bin_opts(test_path: &Path, bin: &str) -> Opts {
+    Opts { bin: Some(bin.to_string()), ..package_opts(test_path) }
+}
+
+/// Output file of a test, named after it so that the tests merging the same package can run in parallel
+fn unique_output_path(test_path: &Path, test_name: &str) -> PathBuf {
+    test_path.join("target/merge").join(format!("{}.rs", test_name))
+}
+
+/// Check that a merged file compiles with rustc
+fn assert_compiles(merged_file_path: &Path) {
+    let status = Command::new("rustc")
+        .args(["--edition", "2018", "--crate-type", "bin", "--emit", "metadata", "--out-dir"])
+        .arg(merged_file_path.parent().unwrap())
+        .arg(merged_file_path)
+        .status()
+        .unwrap();
+    assert!(status.success());
 }
 
 #[test]
 fn simple_binary() {
-    unsafe { reset_base_dir(); }
-    let test_path = "tests_data/simple_binary";
-
-    // Change current directory to the test directory
-    let test_path = detect_package_root().join(test_path);
-    std::env::set_current_dir(&test_path).unwrap();
+    let test_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests_data/simple_binary");
 
-    let opts = Opts { remove_error_output: false, debug: false };
+    let opts = Opts { remove_error_output: false, ..package_opts(&test_path) };
     let merge = Merge::new(opts);
-    merge.run();
+    merge.run().unwrap();
 
     let expected = fs::read_to_string(test_path.join("expected_output.rs")).unwrap();
     let result = fs::read_to_string(test_path.join("target/merge/merged.rs")).unwrap();
@@ -36,35 +48,26 @@ fn simple_binary() {
 
 #[test]
 fn simple_binary_silenced() {
-    unsafe { reset_base_dir(); }
-    let test_path = "tests_data/simple_binary";
-
-    // Change current directory to the test directory
-    let test_path = detect_package_root().join(test_path);
-    std::env::set_current_dir(&test_path).unwrap();
+    let test_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests_data/simple_binary");
 
-    let opts = Opts { remove_error_output: true, debug: false };
+    let output_path = unique_output_path(&test_path, "simple_binary_silenced");
+    let opts = Opts { remove_error_output: true, output: Some(output_path.clone()), ..package_opts(&test_path) };
     let merge = Merge::new(opts);
-    merge.run();
+    merge.run().unwrap();
 
     let expected = fs::read_to_string(test_path.join("expected_output_silenced.rs")).unwrap();
-    let result = fs::read_to_string(test_path.join("target/merge/merged.rs")).unwrap();
+    let result = fs::read_to_string(&output_path).unwrap();
 
     assert_eq!(expected, result);
 }
 
 #[test]
 fn simple_lib() {
-    unsafe { reset_base_dir(); }
-    let test_path = "tests_data/simple_lib";
-
-    // Change current directory to the test directory
-    let test_path = detect_package_root().join(test_path);
-    std::env::set_current_dir(&test_path).unwrap();
+    let test_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests_data/simple_lib");
 
-    let opts = Opts { remove_error_output: false, debug: false };
+    let opts = Opts { remove_error_output: false, ..package_opts(&test_path) };
     let merge = Merge::new(opts);
-    merge.run();
+    merge.run().unwrap();
 
     let expected = fs::read_to_string(test_path.join("expected_output.rs")).unwrap();
     let result = fs::read_to_string(test_path.join("target/merge/merged.rs")).unwrap();
@@ -74,16 +77,11 @@ fn simple_lib() {
 
 #[test]
 fn lib_and_bin() {
-    unsafe { reset_base_dir(); }
-    let test_path = "tests_data/lib_and_bin";
-
-    // Change current directory to the test directory
-    let test_path = detect_package_root().join(test_path);
-    std::env::set_current_dir(&test_path).unwrap();
+    let test_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests_data/lib_and_bin");
 
-    let opts = Opts { remove_error_output: false, debug: false };
+    let opts = Opts { remove_error_output: false, ..package_opts(&test_path) };
     let merge = Merge::new(opts);
-    merge.run();
+    merge.run().unwrap();
 
     let expected = fs::read_to_string(test_path.join("expected_output.rs")).unwrap();
     let result = fs::read_to_string(test_path.join("target/merge/merged.rs")).unwrap();
@@ -93,16 +91,11 @@ fn lib_and_bin() {
 
 #[test]
 fn import_external_lib() {
-    unsafe { reset_base_dir(); }
-    let test_path = "tests_data/import_external_lib";
+    let test_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests_data/import_external_lib");
 
-    // Change current directory to the test directory
-    let test_path = detect_package_root().join(test_path);
-    std::env::set_current_dir(&test_path).unwrap();
-
-    let opts = Opts { remove_error_output: false, debug: false };
+    let opts = Opts { remove_error_output: false, ..package_opts(&test_path) };
     let merge = Merge::new(opts);
-    merge.run();
+    merge.run().unwrap();
 
     let expected = fs::read_to_string(test_path.join("expected_output.rs")).unwrap();
     let result = fs::read_to_string(test_path.join("target/merge/merged.rs")).unwrap();
@@ -112,19 +105,679 @@ fn import_external_lib() {
 
 #[test]
 fn nested_crates() {
-    unsafe { reset_base_dir(); }
-    let test_path = "tests_data/nested_crates";
+    let test_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests_data/nested_crates");
+
+    let opts = Opts { remove_error_output: false, ..package_opts(&test_path) };
+    let merge = Merge::new(opts);
+    merge.run().unwrap();
+
+    let expected = fs::read_to_string(test_path.join("expected_output.rs")).unwrap();
+    let result = fs::read_to_string(test_path.join("target/merge/merged.rs")).unwrap();
+
+    assert_eq!(expected, result);
+}
+
+#[test]
+fn module_declarations() {
+    let test_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests_data/module_declarations");
+
+    let opts = Opts { remove_error_output: false, ..package_opts(&test_path) };
+    let merge = Merge::new(opts);
+    merge.run().unwrap();
+
+    let expected = fs::read_to_string(test_path.join("expected_output.rs")).unwrap();
+    let result = fs::read_to_string(test_path.join("target/merge/merged.rs")).unwrap();
+
+    assert_eq!(expected, result);
+}
+
+#[test]
+fn use_declarations() {
+    let test_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests_data/use_declarations");
+
+    let opts = Opts { remove_error_output: false, ..package_opts(&test_path) };
+    let merge = Merge::new(opts);
+    merge.run().unwrap();
+
+    let expected = fs::read_to_string(test_path.join("expected_output.rs")).unwrap();
+    let result = fs::read_to_string(test_path.join("target/merge/merged.rs")).unwrap();
+
+    assert_eq!(expected, result);
+}
+
+#[test]
+fn crate_segments() {
+    let test_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests_data/crate_segments");
+
+    let opts = Opts { remove_error_output: false, ..package_opts(&test_path) };
+    let merge = Merge::new(opts);
+    merge.run().unwrap();
+
+    let expected = fs::read_to_string(test_path.join("expected_output.rs")).unwrap();
+    let result = fs::read_to_string(test_path.join("target/merge/merged.rs")).unwrap();
+
+    assert_eq!(expected, result);
+}
+
+#[test]
+fn macro_paths() {
+    let test_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests_data/macro_paths");
+
+    // The paths in the tokens of the macro invocations are rewritten too
+    let merge = Merge::new(package_opts(&test_path));
+    merge.run().unwrap();
+
+    let expected = fs::read_to_string(test_path.join("expected_output.rs")).unwrap();
+    let result = fs::read_to_string(test_path.join("target/merge/merged.rs")).unwrap();
+
+    assert_eq!(expected, result);
+    assert_compiles(&test_path.join("target/merge/merged.rs"));
+}
+
+#[test]
+fn path_attributes() {
+    let test_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests_data/path_attributes");
+
+    let opts = Opts { remove_error_output: false, ..package_opts(&test_path) };
+    let merge = Merge::new(opts);
+    merge.run().unwrap();
+
+    let expected = fs::read_to_string(test_path.join("expected_output.rs")).unwrap();
+    let result = fs::read_to_string(test_path.join("target/merge/merged.rs")).unwrap();
+
+    assert_eq!(expected, result);
+}
+
+#[test]
+fn missing_path_attribute() {
+    let test_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests_data/missing_path_attribute");
+
+    let opts = Opts { remove_error_output: false, ..package_opts(&test_path) };
+    let merge = Merge::new(opts);
+    let error = merge.run().unwrap_err();
+    assert!(matches!(error, MergeError::ModuleNotFound { ref module_name, line: 2, .. } if module_name == "missing"));
+    assert!(error.to_string().contains("File not found for module `missing` declared with #[path = \"generated/missing.rs\"]"));
+}
+
+#[test]
+fn module_layouts() {
+    let test_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests_data/module_layouts");
+
+    let opts = Opts { remove_error_output: false, ..package_opts(&test_path) };
+    let merge = Merge::new(opts);
+    merge.run().unwrap();
+
+    let expected = fs::read_to_string(test_path.join("expected_output.rs")).unwrap();
+    let result = fs::read_to_string(test_path.join("target/merge/merged.rs")).unwrap();
+
+    assert_eq!(expected, result);
+}
+
+#[test]
+fn ambiguous_module() {
+    let test_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests_data/ambiguous_module");
+
+    let opts = Opts { remove_error_output: false, ..package_opts(&test_path) };
+    let merge = Merge::new(opts);
+    let error = merge.run().unwrap_err();
+    assert!(matches!(error, MergeError::AmbiguousModule { ref found_paths, line: 1, .. } if found_paths.len() == 2));
+    assert!(error.to_string().contains("ambiguous_module/src/main.rs\" at line 1 found at both"));
+}
+
+#[test]
+fn registry_dependencies() {
+    let test_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests_data/registry_dependencies");
 
-    // Change current directory to the test directory
-    let test_path = detect_package_root().join(test_path);
-    std::env::set_current_dir(&test_path).unwrap();
+    let opts = Opts { merge_registry_dependencies: true, registry_sources: Some(test_path.join("registry")), ..package_opts(&test_path) };
+    let merge = Merge::new(opts);
+    merge.run().unwrap();
+
+    let expected = fs::read_to_string(test_path.join("expected_output.rs")).unwrap();
+    let result = fs::read_to_string(test_path.join("target/merge/merged.rs")).unwrap();
+
+    assert_eq!(expected, result);
+}
+
+#[test]
+fn duplicate_versions() {
+    let test_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests_data/duplicate_versions");
+
+    // Each locked version of a crate is merged into its own module
+    let opts = Opts { merge_registry_dependencies: true, registry_sources: Some(test_path.join("registry")), ..package_opts(&test_path) };
+    let merge = Merge::new(opts);
+    merge.run().unwrap();
+
+    let expected = fs::read_to_string(test_path.join("expected_output.rs")).unwrap();
+    let result = fs::read_to_string(test_path.join("target/merge/merged.rs")).unwrap();
+
+    assert_eq!(expected, result);
+    assert_compiles(&test_path.join("target/merge/merged.rs"));
+}
+
+#[test]
+fn cargo_workspace() {
+    let test_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests_data/cargo_workspace/app");
+
+    let opts = Opts { remove_error_output: false, ..package_opts(&test_path) };
+    let merge = Merge::new(opts);
+    merge.run().unwrap();
+
+    let expected = fs::read_to_string(test_path.join("expected_output.rs")).unwrap();
+    let result = fs::read_to_string(test_path.join("target/merge/merged.rs")).unwrap();
+
+    assert_eq!(expected, result);
+}
+
+#[test]
+fn custom_targets() {
+    let test_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests_data/custom_targets");
+
+    let opts = Opts { remove_error_output: false, ..package_opts(&test_path) };
+    let merge = Merge::new(opts);
+    merge.run().unwrap();
+
+    let expected = fs::read_to_string(test_path.join("expected_output.rs")).unwrap();
+    let result = fs::read_to_string(test_path.join("target/merge/merged.rs")).unwrap();
+
+    assert_eq!(expected, result);
+}
+
+#[test]
+fn bin_layout() {
+    let test_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests_data/bin_layout");
+
+    let output_path = unique_output_path(&test_path, "bin_layout");
+    let opts = Opts { remove_error_output: false, output: Some(output_path.clone()), ..package_opts(&test_path) };
+    let merge = Merge::new(opts);
+    merge.run().unwrap();
+
+    let expected = fs::read_to_string(test_path.join("expected_output.rs")).unwrap();
+    let result = fs::read_to_string(&output_path).unwrap();
+
+    assert_eq!(expected, result);
+}
 
-    let opts = Opts { remove_error_output: false, debug: false };
+#[test]
+fn bin_submodules() {
+    let test_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests_data/bin_submodules");
+
+    // The modules of the binary use the lib crate of the package by its name
+    let merge = Merge::new(package_opts(&test_path));
+    merge.run().unwrap();
+
+    let expected = fs::read_to_string(test_path.join("expected_output.rs")).unwrap();
+    let result = fs::read_to_string(test_path.join("target/merge/merged.rs")).unwrap();
+
+    assert_eq!(expected, result);
+    assert_compiles(&test_path.join("target/merge/merged.rs"));
+}
+
+#[test]
+fn multi_bins_second() {
+    let test_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests_data/multi_bins");
+
+    let output_path = unique_output_path(&test_path, "multi_bins_second");
+    let opts = Opts { bin: Some(String::from("second")), output: Some(output_path.clone()), ..package_opts(&test_path) };
+    let merge = Merge::new(opts);
+    merge.run().unwrap();
+
+    let expected = fs::read_to_string(test_path.join("expected_output_second.rs")).unwrap();
+    let result = fs::read_to_string(&output_path).unwrap();
+
+    assert_eq!(expected, result);
+}
+
+#[test]
+fn multi_bins_demo() {
+    let test_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests_data/multi_bins");
+
+    let opts = Opts { example: Some(String::from("demo")), ..package_opts(&test_path) };
+    let merge = Merge::new(opts);
+    merge.run().unwrap();
+
+    let expected = fs::read_to_string(test_path.join("expected_output_demo.rs")).unwrap();
+    let result = fs::read_to_string(test_path.join("target/merge/demo.rs")).unwrap();
+
+    assert_eq!(expected, result);
+}
+
+#[test]
+fn multi_bins_all() {
+    let test_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests_data/multi_bins");
+
+    let opts = Opts { all_bins: true, ..package_opts(&test_path) };
+    let merge = Merge::new(opts);
+    merge.run().unwrap();
+
+    for bin_name in &["first", "second"] {
+        let expected = fs::read_to_string(test_path.join(format!("expected_output_{}.rs", bin_name))).unwrap();
+        let result = fs::read_to_string(test_path.join(format!("target/merge/{}.rs", bin_name))).unwrap();
+
+        assert_eq!(expected, result);
+    }
+}
+
+#[test]
+fn tree_shaking() {
+    let test_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests_data/tree_shaking");
+
+    let opts = Opts { tree_shake: true, ..package_opts(&test_path) };
     let merge = Merge::new(opts);
-    merge.run();
+    merge.run().unwrap();
 
     let expected = fs::read_to_string(test_path.join("expected_output.rs")).unwrap();
     let result = fs::read_to_string(test_path.join("target/merge/merged.rs")).unwrap();
 
     assert_eq!(expected, result);
-}
\ No newline at end of file
+
+    // The pruned output must still compile
+    assert_compiles(&test_path.join("target/merge/merged.rs"));
+}
+
+#[test]
+fn max_size() {
+    let test_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests_data/cargo_workspace/app");
+
+    let output_path = unique_output_path(&test_path, "max_size");
+    let opts = Opts { max_size: Some(SizeLimit { size: 409, unit: SizeUnit::Bytes }), output: Some(output_path.clone()), ..package_opts(&test_path) };
+    let merge = Merge::new(opts);
+    merge.run().unwrap();
+
+    let expected = fs::read_to_string(test_path.join("expected_output.rs")).unwrap();
+    let result = fs::read_to_string(&output_path).unwrap();
+
+    assert_eq!(expected, result);
+}
+
+#[test]
+fn max_size_exceeded() {
+    let test_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests_data/cargo_workspace/app");
+
+    let opts = Opts { max_size: Some("300chars".parse().unwrap()), output: Some(unique_output_path(&test_path, "max_size_exceeded")), ..package_opts(&test_path) };
+    let merge = Merge::new(opts);
+    let error = merge.run().unwrap_err();
+    assert!(matches!(error, MergeError::SizeLimitExceeded { size: 409, .. }));
+    assert!(error.to_string().contains("is 409 chars, over the limit of 300 chars"));
+}
+
+#[test]
+fn minify() {
+    let test_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests_data/minify");
+
+    let opts = Opts { minify: true, ..package_opts(&test_path) };
+    let merge = Merge::new(opts);
+    merge.run().unwrap();
+
+    let expected = fs::read_to_string(test_path.join("expected_output.rs")).unwrap();
+    let result = fs::read_to_string(test_path.join("target/merge/merged.rs")).unwrap();
+
+    assert_eq!(expected, result);
+    assert_compiles(&test_path.join("target/merge/merged.rs"));
+}
+
+#[test]
+fn doc_comments() {
+    let test_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests_data/doc_comments");
+
+    let opts = Opts { remove_doc_comments: true, ..package_opts(&test_path) };
+    let merge = Merge::new(opts);
+    merge.run().unwrap();
+
+    let expected = fs::read_to_string(test_path.join("expected_output.rs")).unwrap();
+    let result = fs::read_to_string(test_path.join("target/merge/merged.rs")).unwrap();
+
+    assert_eq!(expected, result);
+}
+
+#[test]
+fn test_items() {
+    let test_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests_data/test_items");
+
+    // Test items are removed by default
+    let merge = Merge::new(package_opts(&test_path));
+    merge.run().unwrap();
+
+    let expected = fs::read_to_string(test_path.join("expected_output.rs")).unwrap();
+    let result = fs::read_to_string(test_path.join("target/merge/merged.rs")).unwrap();
+
+    assert_eq!(expected, result);
+
+    let opts = Opts { keep_tests: true, ..package_opts(&test_path) };
+    let merge = Merge::new(opts);
+    merge.run().unwrap();
+
+    let expected = fs::read_to_string(test_path.join("expected_output_with_tests.rs")).unwrap();
+    let result = fs::read_to_string(test_path.join("target/merge/merged.rs")).unwrap();
+
+    assert_eq!(expected, result);
+}
+
+#[test]
+fn features() {
+    let test_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests_data/features");
+
+    // Default features
+    let merge = Merge::new(package_opts(&test_path));
+    merge.run().unwrap();
+
+    let expected = fs::read_to_string(test_path.join("expected_output.rs")).unwrap();
+    let result = fs::read_to_string(test_path.join("target/merge/merged.rs")).unwrap();
+
+    assert_eq!(expected, result);
+
+    // Selected features, one of them enabling an optional dependency and one of its features
+    let opts = Opts { features: vec![String::from("slow,loud")], no_default_features: true, ..package_opts(&test_path) };
+    let merge = Merge::new(opts);
+    merge.run().unwrap();
+
+    let expected = fs::read_to_string(test_path.join("expected_output_slow_loud.rs")).unwrap();
+    let result = fs::read_to_string(test_path.join("target/merge/merged.rs")).unwrap();
+
+    assert_eq!(expected, result);
+}
+
+#[test]
+fn target_cfgs() {
+    let test_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests_data/target_cfgs");
+
+    let opts = Opts { target_profile: Some(TargetProfile::Linux), cfg: vec![String::from("judge")], ..package_opts(&test_path) };
+    let merge = Merge::new(opts);
+    merge.run().unwrap();
+
+    let expected = fs::read_to_string(test_path.join("expected_output.rs")).unwrap();
+    let result = fs::read_to_string(test_path.join("target/merge/merged.rs")).unwrap();
+
+    assert_eq!(expected, result);
+}
+
+#[test]
+fn removed_items() {
+    let test_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests_data/removed_items");
+
+    // Consecutive items removed by cfg evaluation are all removed, along with the blank lines between them
+    let merge = Merge::new(package_opts(&test_path));
+    merge.run().unwrap();
+
+    let expected = fs::read_to_string(test_path.join("expected_output.rs")).unwrap();
+    let result = fs::read_to_string(test_path.join("target/merge/merged.rs")).unwrap();
+
+    assert_eq!(expected, result);
+
+    assert_compiles(&test_path.join("target/merge/merged.rs"));
+}
+
+#[test]
+fn cfg_positions() {
+    let test_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests_data/cfg_positions");
+
+    // The cfg attributes of struct literal fields, field patterns, expressions, parameters and generic parameters are evaluated too,
+    // as the judge compiles the merged file without the features
+    let merge = Merge::new(package_opts(&test_path));
+    merge.run().unwrap();
+
+    let expected = fs::read_to_string(test_path.join("expected_output.rs")).unwrap();
+    let result = fs::read_to_string(test_path.join("target/merge/merged.rs")).unwrap();
+
+    assert_eq!(expected, result);
+
+    assert_compiles(&test_path.join("target/merge/merged.rs"));
+}
+
+#[test]
+fn platform_dependencies() {
+    let test_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests_data/platform_dependencies");
+
+    // The dependencies declared for other platforms, by cfg predicate or target triple, are not merged
+    let opts = Opts { target_profile: Some(TargetProfile::Linux), ..package_opts(&test_path) };
+    let merge = Merge::new(opts);
+    merge.run().unwrap();
+
+    let expected = fs::read_to_string(test_path.join("expected_output.rs")).unwrap();
+    let result = fs::read_to_string(test_path.join("target/merge/merged.rs")).unwrap();
+
+    assert_eq!(expected, result);
+}
+
+#[test]
+fn check() {
+    let test_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests_data/lib_and_bin");
+
+    let opts = Opts { check: true, output: Some(unique_output_path(&test_path, "check")), ..package_opts(&test_path) };
+    let merge = Merge::new(opts);
+    merge.run().unwrap();
+}
+
+#[test]
+fn check_lib() {
+    let test_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests_data/simple_lib");
+
+    // Without a binary target, the merged file is checked as a library
+    let opts = Opts { check: true, output: Some(unique_output_path(&test_path, "check_lib")), ..package_opts(&test_path) };
+    let merge = Merge::new(opts);
+    merge.run().unwrap();
+}
+
+#[test]
+fn check_errors() {
+    let test_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests_data/check_errors");
+
+    let opts = Opts { check: true, tree_shake: true, ..package_opts(&test_path) };
+    let merge = Merge::new(opts);
+    let error = merge.run().unwrap_err();
+    match error {
+        MergeError::CompileErrors { errors, .. } => {
+            assert_eq!(errors.len(), 1);
+            assert_eq!(errors[0].message, "mismatched types");
+            assert!(errors[0].location.ends_with("area.rs:5 (crate check_errors, merged at line 8)"));
+        }
+        error => panic!("Unexpected error: {}", error),
+    }
+}
+
+#[test]
+fn test_samples() {
+    let test_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests_data/sample_tests");
+
+    // Outputs are compared ignoring trailing whitespace and empty lines
+    let opts = Opts { test_samples: Some(test_path.join("samples")), ..package_opts(&test_path) };
+    let merge = Merge::new(opts);
+    merge.run().unwrap();
+}
+
+#[test]
+fn test_samples_failed() {
+    let test_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests_data/sample_tests");
+
+    let opts = Opts { test_samples: Some(test_path.join("wrong_samples")), ..bin_opts(&test_path, "sample_tests") };
+    let merge = Merge::new(opts);
+    let error = merge.run().unwrap_err();
+    assert!(matches!(error, MergeError::SamplesFailed { ref failed_samples, sample_count: 2 } if failed_samples == &["2.in"]));
+}
+
+#[test]
+fn test_samples_large_io() {
+    let test_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests_data/sample_io");
+
+    // The input and the output are larger than the pipe buffers, and the binary writes before reading everything
+    let samples_path = test_path.join("target/large_samples");
+    fs::create_dir_all(&samples_path).unwrap();
+    let sample: String = (0..100_000).map(|index| format!("line {}\n", index)).collect();
+    fs::write(samples_path.join("1.in"), &sample).unwrap();
+    fs::write(samples_path.join("1.out"), &sample).unwrap();
+
+    let opts = Opts { test_samples: Some(samples_path), output: Some(unique_output_path(&test_path, "test_samples_large_io")), ..package_opts(&test_path) };
+    let merge = Merge::new(opts);
+    merge.run().unwrap();
+}
+
+#[test]
+fn test_samples_timeout() {
+    let test_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests_data/sample_io");
+
+    // The binary never ends on this sample, it is stopped and the sample failed
+    let samples_path = test_path.join("target/timeout_samples");
+    fs::create_dir_all(&samples_path).unwrap();
+    fs::write(samples_path.join("1.in"), "loop\n").unwrap();
+    fs::write(samples_path.join("1.out"), "").unwrap();
+
+    let opts = Opts { test_samples: Some(samples_path), sample_timeout: Some(0.5), output: Some(unique_output_path(&test_path, "test_samples_timeout")), ..package_opts(&test_path) };
+    let merge = Merge::new(opts);
+    let error = merge.run().unwrap_err();
+    assert!(matches!(error, MergeError::SamplesFailed { ref failed_samples, sample_count: 1 } if failed_samples == &["1.in"]));
+}
+
+#[test]
+fn source_map() {
+    let test_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests_data/check_errors");
+
+    let merge = Merge::new(bin_opts(&test_path, "check_errors"));
+    merge.run().unwrap();
+
+    let source_map = fs::read_to_string(test_path.join("target/merge/check_errors.map.json")).unwrap();
+    let source_map: serde_json::Value = serde_json::from_str(&source_map).unwrap();
+    assert_eq!(source_map["file"], "check_errors.rs");
+    // The lines of the nested module file are followed by the rest of their parent module file
+    let ranges: Vec<(u64, u64, String, u64)> = source_map["ranges"].as_array().unwrap().iter()
+        .map(|range| (
+            range["start"].as_u64().unwrap(),
+            range["end"].as_u64().unwrap(),
+            Path::new(range["file"].as_str().unwrap()).strip_prefix(&test_path).unwrap().to_str().unwrap().replace('\\', "/"),
+            range["line"].as_u64().unwrap(),
+        ))
+        .collect();
+    assert_eq!(ranges, vec![
+        (4, 10, "src/geometry/area.rs".to_string(), 1),
+        (13, 17, "src/geometry.rs".to_string(), 2),
+        (22, 27, "src/main.rs".to_string(), 1),
+    ]);
+    assert!(source_map["ranges"].as_array().unwrap().iter().all(|range| range["crate"] == "check_errors"));
+
+    // Lines are located from the source map, the generated ones have no origin
+    let origin = merge.locate(8, None).unwrap().unwrap();
+    assert_eq!((origin.file_path, origin.line, origin.crate_name.as_str()), (test_path.join("src/geometry/area.rs"), 5, "check_errors"));
+    assert_eq!(merge.locate(1, None).unwrap(), None);
+
+    let invalid_map_path = test_path.join("target/merge/invalid.map.json");
+    fs::write(&invalid_map_path, "{").unwrap();
+    assert!(matches!(merge.locate(8, Some(&invalid_map_path)), Err(MergeError::SourceMap { .. })));
+}
+
+#[test]
+fn copy_to() {
+    let test_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests_data/module_declarations");
+
+    let opts = Opts { copy_to: Some(test_path.join("target/copied.rs")), output: Some(unique_output_path(&test_path, "copy_to")), ..package_opts(&test_path) };
+    let merge = Merge::new(opts);
+    merge.run().unwrap();
+
+    let expected = fs::read_to_string(test_path.join("expected_output.rs")).unwrap();
+    let result = fs::read_to_string(test_path.join("target/copied.rs")).unwrap();
+
+    assert_eq!(expected, result);
+}
+
+#[test]
+fn output_path() {
+    let test_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests_data/crate_segments");
+
+    // The source map is written alongside the output file
+    let opts = Opts { output: Some(test_path.join("target/solution.rs")), ..package_opts(&test_path) };
+    let merge = Merge::new(opts);
+    merge.run().unwrap();
+
+    let expected = fs::read_to_string(test_path.join("expected_output.rs")).unwrap();
+    let result = fs::read_to_string(test_path.join("target/solution.rs")).unwrap();
+
+    assert_eq!(expected, result);
+    assert!(test_path.join("target/solution.map.json").is_file());
+}
+
+#[test]
+fn manifest_path() {
+    let test_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests_data/nested_crates");
+    let current_dir = std::env::current_dir().unwrap();
+
+    // The dependencies paths are resolved from the package root, without changing the current directory
+    let output_path = unique_output_path(&test_path, "manifest_path");
+    let opts = Opts { output: Some(output_path.clone()), ..package_opts(&test_path) };
+    let merge = Merge::new(opts);
+    merge.run().unwrap();
+
+    let expected = fs::read_to_string(test_path.join("expected_output.rs")).unwrap();
+    let result = fs::read_to_string(&output_path).unwrap();
+
+    assert_eq!(expected, result);
+    assert_eq!(current_dir, std::env::current_dir().unwrap());
+}
+
+#[test]
+fn output_stdout() {
+    let test_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests_data/bin_layout");
+
+    // The merged file is the only output on stdout, the status messages go to stderr
+    let output = Command::new(env!("CARGO_BIN_EXE_cargo-merge"))
+        .args(["merge", "-o", "-"])
+        .current_dir(&test_path)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let expected = fs::read_to_string(test_path.join("expected_output.rs")).unwrap();
+    assert_eq!(expected, String::from_utf8(output.stdout).unwrap());
+    assert!(String::from_utf8(output.stderr).unwrap().contains("Merged"));
+}
+
+#[test]
+fn error_exit_code() {
+    let test_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests_data/multi_bins");
+
+    // Failures are reported as diagnostics, with a nonzero exit code
+    let output = Command::new(env!("CARGO_BIN_EXE_cargo-merge"))
+        .args(["merge", "--bin", "missing"])
+        .current_dir(&test_path)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Binary target missing not found, available targets are: "));
+    assert!(!stderr.contains("panicked"));
+}
+
+#[test]
+fn builder() {
+    let test_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests_data/lib_and_bin");
+
+    // Nothing is written, the merged source is returned
+    let output = MergeBuilder::new(&test_path).merge().unwrap();
+
+    let expected = fs::read_to_string(test_path.join("expected_output.rs")).unwrap();
+    assert_eq!(expected, output.source);
+    assert_eq!(output.package_name, "lib_and_bin");
+    assert_eq!(output.target_name.as_deref(), Some("lib_and_bin"));
+    assert_eq!(output.edition, "2018");
+    assert_eq!(output.library_modules, vec!["lib_and_bin"]);
+    assert!(output.files.contains(&test_path.join("src/hello_world.rs")));
+    let origin = output.origin(4).unwrap();
+    assert_eq!((origin.file_path.as_path(), origin.line), (test_path.join("src/hello_world.rs").as_path(), 2));
+    assert_eq!(output.origin(1), None);
+}
+
+#[test]
+fn builder_targets() {
+    let test_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests_data/multi_bins");
+
+    let output = MergeBuilder::new(test_path.join("Cargo.toml")).target(Target::Example(String::from("demo"))).merge().unwrap();
+    assert_eq!(fs::read_to_string(test_path.join("expected_output_demo.rs")).unwrap(), output.source);
+
+    let outputs = MergeBuilder::new(&test_path).merge_all_bins().unwrap();
+    let target_names: Vec<&str> = outputs.iter().filter_map(|output| output.target_name.as_deref()).collect();
+    assert_eq!(target_names, vec!["first", "second"]);
+    for output in outputs {
+        let expected_path = test_path.join(format!("expected_output_{}.rs", output.target_name.unwrap()));
+        assert_eq!(fs::read_to_string(expected_path).unwrap(), output.source);
+    }
+
+    let error = MergeBuilder::new(&test_path).target(Target::Bin(String::from("missing"))).merge().unwrap_err();
+    assert!(matches!(error, MergeError::TargetNotFound { ref available_targets, .. } if available_targets == &["first", "second"]));
+}
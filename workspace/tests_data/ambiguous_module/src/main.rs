@@ -0,0 +1,5 @@
+mod twice;
+
+fn main() {
+    twice::run();
+}
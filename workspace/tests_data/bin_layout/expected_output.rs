@@ -0,0 +1,15 @@
+pub mod bin_layout {
+pub mod answer {
+pub fn answer() -> u32 {
+    42
+}
+
+}
+
+}
+use crate::bin_layout::answer::answer;
+
+fn main() {
+    println!("{}", answer());
+}
+
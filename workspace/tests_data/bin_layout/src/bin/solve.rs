@@ -0,0 +1,5 @@
+use bin_layout::answer::answer;
+
+fn main() {
+    println!("{}", answer());
+}
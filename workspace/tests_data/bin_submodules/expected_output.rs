@@ -0,0 +1,19 @@
+pub mod bin_submodules {
+pub fn answer() -> u32 {
+    42
+}
+
+}
+pub mod solver {
+use crate::bin_submodules::answer;
+
+pub fn solve() -> u32 {
+    answer() + crate::bin_submodules::answer()
+}
+
+}
+
+fn main() {
+    println!("{}", solver::solve());
+}
+
@@ -0,0 +1,5 @@
+use bin_submodules::answer;
+
+pub fn solve() -> u32 {
+    answer() + bin_submodules::answer()
+}
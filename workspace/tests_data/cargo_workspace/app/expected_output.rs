@@ -0,0 +1,26 @@
+pub mod geometry {
+use crate::numbers::square;
+
+pub fn squared_norm(x: i64, y: i64) -> i64 {
+    square(x) + square(y)
+}
+
+}
+pub mod io_helpers {
+pub fn print(value: i64) {
+    println!("{}", value);
+}
+
+}
+pub mod numbers {
+pub fn square(value: i64) -> i64 {
+    value * value
+}
+
+}
+use crate::geometry::squared_norm;
+
+fn main() {
+    crate::io_helpers::print(crate::numbers::square(2) + squared_norm(3, 4));
+}
+
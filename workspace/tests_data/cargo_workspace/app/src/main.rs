@@ -0,0 +1,5 @@
+use geo::squared_norm;
+
+fn main() {
+    io_helpers::print(numbers::square(2) + squared_norm(3, 4));
+}
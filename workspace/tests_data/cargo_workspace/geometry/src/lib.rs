@@ -0,0 +1,5 @@
+use numbers::square;
+
+pub fn squared_norm(x: i64, y: i64) -> i64 {
+    square(x) + square(y)
+}
@@ -0,0 +1,3 @@
+pub fn print(value: i64) {
+    println!("{}", value);
+}
@@ -0,0 +1,3 @@
+pub fn square(value: i64) -> i64 {
+    value * value
+}
@@ -0,0 +1,20 @@
+struct Point {
+    x: i32,
+    y: i32,
+}
+
+fn weighted<>(point: &Point, weight: i32) -> i32 {
+    let Point { x, y, } = point;
+    (x + y) * weight
+}
+
+fn main() {
+    let point = Point {
+        x: 1,
+        y: 2,
+    };
+    let values = vec![point.x, point.y];
+    let array = [point.x, point.y];
+    println!("{} {:?} {:?}", weighted(&point, 3), values, array);
+}
+
@@ -0,0 +1,27 @@
+struct Point {
+    x: i32,
+    #[cfg(feature = "extra")]
+    y: i32,
+    #[cfg(feature = "other")]
+    z: i32,
+}
+
+fn weighted<#[cfg(feature = "other")] 'a>(point: &Point, #[cfg(feature = "extra")] weight: i32, #[cfg(feature = "other")] offset: i32) -> i32 {
+    let Point { x, #[cfg(feature = "extra")] y, #[cfg(feature = "other")] z, } = point;
+    (x + y) * weight
+}
+
+fn main() {
+    let point = Point {
+        x: 1,
+        #[cfg(feature = "extra")]
+        y: 2,
+        #[cfg(feature = "other")]
+        z: 3,
+    };
+    let values = vec![point.x, #[cfg(feature = "extra")] point.y, #[cfg(feature = "other")] point.z];
+    let array = [point.x, #[cfg(feature = "extra")] point.y, #[cfg(feature = "other")] point.z];
+    #[cfg(feature = "other")]
+    weighted(&point, 1, 2);
+    println!("{} {:?} {:?}", weighted(&point, 3), values, array);
+}
@@ -0,0 +1,6 @@
+pub mod area;
+
+pub struct Rectangle {
+    pub width: u32,
+    pub height: u32,
+}
@@ -0,0 +1,7 @@
+use super::Rectangle;
+
+/// Area of a rectangle
+pub fn area(rectangle: &Rectangle) -> u64 {
+    let area: u64 = rectangle.width * rectangle.height;
+    area
+}
@@ -0,0 +1,6 @@
+use check_errors::geometry::{area::area, Rectangle};
+
+fn main() {
+    let rectangle = Rectangle { width: 3, height: 4 };
+    println!("{}", area(&rectangle));
+}
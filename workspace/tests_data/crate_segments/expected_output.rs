@@ -0,0 +1,75 @@
+pub mod simple_lib {
+pub mod hello_world {
+
+pub fn hello_world() {
+    println!("Hello, world!");
+    eprintln!("yeah");
+}
+
+}
+pub mod call {
+use crate::simple_lib::hello_world::hello_world;
+
+pub fn call() {
+    hello_world();
+}
+
+}
+
+}
+pub mod crate_segments {
+pub mod crate_tools {
+use crate::crate_segments::simple_lib_extensions::{self, call_twice};
+
+pub const NAME: &str = "crate_segments";
+
+pub(crate) fn call_all() {
+    call_twice();
+    simple_lib_extensions::call_once();
+}
+
+pub fn call_from_crate_root() -> &'static str {
+    call_all();
+    crate::crate_segments::my_crate_name()
+}
+
+}
+pub mod simple_lib_extensions {
+use self::inner::twice;
+use super::crate_tools::NAME;
+
+mod inner {
+    pub(in crate::crate_segments::simple_lib_extensions) fn twice(function: fn()) {
+        function();
+        function();
+    }
+}
+
+pub fn call_once() {
+    crate::simple_lib::call::call();
+}
+
+pub fn call_twice() {
+    twice(crate::simple_lib::call::call);
+    println!("{}", NAME);
+}
+
+}
+
+macro_rules! crate_name {
+    () => {
+        $crate::crate_segments::crate_tools::NAME
+    };
+}
+
+pub fn my_crate_name() -> &'static str {
+    crate_name!()
+}
+
+}
+use crate::crate_segments::crate_tools::call_from_crate_root;
+
+fn main() {
+    println!("{}", call_from_crate_root());
+}
+
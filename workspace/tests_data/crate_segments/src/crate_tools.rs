@@ -0,0 +1,13 @@
+use crate::simple_lib_extensions::{self, call_twice};
+
+pub const NAME: &str = "crate_segments";
+
+pub(crate) fn call_all() {
+    call_twice();
+    simple_lib_extensions::call_once();
+}
+
+pub fn call_from_crate_root() -> &'static str {
+    call_all();
+    crate::my_crate_name()
+}
@@ -0,0 +1,12 @@
+pub mod crate_tools;
+pub mod simple_lib_extensions;
+
+macro_rules! crate_name {
+    () => {
+        $crate::crate_tools::NAME
+    };
+}
+
+pub fn my_crate_name() -> &'static str {
+    crate_name!()
+}
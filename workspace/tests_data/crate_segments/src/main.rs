@@ -0,0 +1,5 @@
+use crate_segments::crate_tools::call_from_crate_root;
+
+fn main() {
+    println!("{}", call_from_crate_root());
+}
@@ -0,0 +1,18 @@
+use self::inner::twice;
+use super::crate_tools::NAME;
+
+mod inner {
+    pub(in crate::simple_lib_extensions) fn twice(function: fn()) {
+        function();
+        function();
+    }
+}
+
+pub fn call_once() {
+    ::simple_lib::call::call();
+}
+
+pub fn call_twice() {
+    twice(simple_lib::call::call);
+    println!("{}", NAME);
+}
@@ -0,0 +1,23 @@
+pub mod algo {
+pub mod sort {
+pub fn sorted(mut values: Vec<i32>) -> Vec<i32> {
+    values.sort();
+    values
+}
+
+}
+
+}
+pub mod input {
+pub fn values() -> Vec<i32> {
+    vec![3, 1, 2]
+}
+
+}
+
+use crate::algo::sort::sorted;
+
+fn main() {
+    println!("{:?}", sorted(input::values()));
+}
+
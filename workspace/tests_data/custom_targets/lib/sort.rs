@@ -0,0 +1,4 @@
+pub fn sorted(mut values: Vec<i32>) -> Vec<i32> {
+    values.sort();
+    values
+}
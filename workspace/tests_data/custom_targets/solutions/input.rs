@@ -0,0 +1,3 @@
+pub fn values() -> Vec<i32> {
+    vec![3, 1, 2]
+}
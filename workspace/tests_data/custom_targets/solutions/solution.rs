@@ -0,0 +1,7 @@
+mod input;
+
+use algo::sort::sorted;
+
+fn main() {
+    println!("{:?}", sorted(input::values()));
+}
@@ -0,0 +1,34 @@
+pub mod doc_comments {
+
+pub mod shapes {
+
+#[derive(Debug)]
+pub struct Square {
+    pub side: u32,
+}
+
+impl Square {
+    pub fn new(side: u32) -> Square {
+        Square { side }
+    }
+
+    pub fn area(&self) -> u32 { self.side * self.side }
+}
+
+}
+
+pub const ANSWER: u32 = 42;
+
+// A regular comment is kept
+#[doc(hidden)]
+pub fn hidden() -> u32 {
+    ANSWER
+}
+
+}
+use crate::doc_comments::shapes::Square;
+
+fn main() {
+    println!("{} {}", Square::new(3).area(), crate::doc_comments::hidden());
+}
+
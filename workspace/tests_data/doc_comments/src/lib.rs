@@ -0,0 +1,20 @@
+//! Documented library
+//!
+//! ```
+//! assert_eq!(doc_comments::shapes::Square::new(2).area(), 4);
+//! ```
+#![doc = "Attribute documentation"]
+
+/// Shapes
+/// with areas
+pub mod shapes;
+
+/** The answer, documented
+    with a block comment */
+pub const ANSWER: u32 = 42;
+
+// A regular comment is kept
+#[doc(hidden)]
+pub fn hidden() -> u32 {
+    ANSWER
+}
@@ -0,0 +1,6 @@
+use doc_comments::shapes::Square;
+
+/// Entry point
+fn main() {
+    println!("{} {}", Square::new(3).area(), doc_comments::hidden());
+}
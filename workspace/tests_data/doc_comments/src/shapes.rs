@@ -0,0 +1,22 @@
+//! Shapes module
+
+/// A square
+#[derive(Debug)]
+pub struct Square {
+    /// Length of a side
+    pub side: u32,
+}
+
+impl Square {
+    /// Build a square
+    ///
+    /// ```
+    /// let square = doc_comments::shapes::Square::new(3);
+    /// ```
+    pub fn new(side: u32) -> Square {
+        Square { side }
+    }
+
+    #[doc = "Area of the square"]
+    pub fn area(&self) -> u32 { self.side * self.side }
+}
@@ -0,0 +1,43 @@
+pub mod legacy {
+pub fn reduce(a: u64, b: u64) -> u64 {
+    crate::tiny_math::gcd::gcd(a, b)
+}
+
+}
+pub mod tiny_math {
+pub mod gcd {
+/// Greatest common divisor
+pub fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 {
+        a
+    } else {
+        crate::tiny_math::gcd::gcd(b, a % b)
+    }
+}
+
+}
+
+}
+pub mod tiny_math_0_2_0 {
+pub mod gcd {
+/// Greatest common divisor
+pub fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 {
+        a
+    } else {
+        crate::tiny_math_0_2_0::gcd::gcd(b, a % b)
+    }
+}
+
+}
+
+/// Least common multiple
+pub fn lcm(a: u64, b: u64) -> u64 {
+    a / gcd::gcd(a, b) * b
+}
+
+}
+fn main() {
+    println!("{} {}", crate::legacy::reduce(12, 18), crate::tiny_math_0_2_0::lcm(4, 6));
+}
+
@@ -0,0 +1,3 @@
+pub fn reduce(a: u64, b: u64) -> u64 {
+    tiny_math::gcd::gcd(a, b)
+}
@@ -0,0 +1,6 @@
+pub mod gcd;
+
+/// Least common multiple
+pub fn lcm(a: u64, b: u64) -> u64 {
+    a / gcd::gcd(a, b) * b
+}
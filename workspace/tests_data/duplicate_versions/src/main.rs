@@ -0,0 +1,3 @@
+fn main() {
+    println!("{} {}", legacy::reduce(12, 18), tiny_math::lcm(4, 6));
+}
@@ -0,0 +1,47 @@
+pub mod features {
+pub fn solve(n: u64) -> u64 {
+    n * (n + 1) / 2
+}
+
+pub const MODE: &str = "configured";
+
+#[cfg(unix)]
+pub fn platform() -> &'static str {
+    "unix"
+}
+
+#[derive(Debug)]
+#[derive(Clone, Copy)]
+pub struct Answer {
+    pub value: u64,
+}
+
+impl Answer {
+    pub fn new(value: u64) -> Answer {
+        Answer { value }
+    }
+}
+
+pub enum Mode {
+    Fast,
+}
+
+pub fn describe(mode: &Mode) -> &'static str {
+    match mode {
+        Mode::Fast => "fast",
+    }
+}
+
+pub fn is_loud() -> bool {
+    false
+}
+
+}
+use crate::features::{describe, solve, Answer, Mode};
+
+fn main() {
+    let answer = Answer::new(solve(10));
+    println!("{:?} {} {}", answer, crate::features::MODE, describe(&Mode::Fast));
+    println!("{} {}", crate::features::platform(), crate::features::is_loud());
+}
+
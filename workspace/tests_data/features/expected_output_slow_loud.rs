@@ -0,0 +1,67 @@
+pub mod helpers {
+pub fn greet() -> String {
+    String::from("HELLO")
+}
+
+}
+pub mod features {
+pub fn solve(n: u64) -> u64 {
+    (1..=n).sum()
+}
+
+#[cfg(unix)]
+pub mod slow {
+pub fn trace(value: u64) -> [u64; 1] {
+    [value]
+}
+
+}
+
+pub const MODE: &str = "configured";
+
+pub fn platform() -> &'static str {
+    "unix"
+}
+
+#[derive(Debug)]
+pub struct Answer {
+    pub value: u64,
+    pub trace: [u64; 1],
+}
+
+impl Answer {
+    pub fn new(value: u64) -> Answer {
+        Answer { value, trace: [value] }
+    }
+}
+
+pub enum Mode {
+    Fast,
+    Slow,
+}
+
+pub fn describe(mode: &Mode) -> &'static str {
+    match mode {
+        Mode::Fast => "fast",
+        Mode::Slow => "slow",
+    }
+}
+
+pub fn is_loud() -> bool {
+    true
+}
+
+pub fn greet() -> String {
+    crate::helpers::greet()
+}
+
+}
+use crate::features::{describe, solve, Answer, Mode};
+
+fn main() {
+    let answer = Answer::new(solve(10));
+    println!("{:?} {} {}", answer, crate::features::MODE, describe(&Mode::Fast));
+    println!("{} {}", crate::features::platform(), crate::features::is_loud());
+    println!("{}", crate::features::greet());
+}
+
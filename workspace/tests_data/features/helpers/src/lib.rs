@@ -0,0 +1,9 @@
+#[cfg(feature = "shout")]
+pub fn greet() -> String {
+    String::from("HELLO")
+}
+
+#[cfg(not(feature = "shout"))]
+pub fn greet() -> String {
+    String::from("hello")
+}
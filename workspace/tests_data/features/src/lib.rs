@@ -0,0 +1,63 @@
+#[cfg(feature = "fast")]
+pub fn solve(n: u64) -> u64 {
+    n * (n + 1) / 2
+}
+
+#[cfg(not(feature = "fast"))]
+pub fn solve(n: u64) -> u64 {
+    (1..=n).sum()
+}
+
+#[cfg(all(feature = "slow", unix))]
+pub mod slow;
+
+#[cfg(any(feature = "fast", feature = "slow"))]
+pub const MODE: &str = "configured";
+
+#[cfg(any(feature = "loud", unix))]
+pub fn platform() -> &'static str {
+    "unix"
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "fast", derive(Clone, Copy))]
+pub struct Answer {
+    pub value: u64,
+    #[cfg(feature = "slow")]
+    pub trace: [u64; 1],
+}
+
+impl Answer {
+    #[cfg(feature = "slow")]
+    pub fn new(value: u64) -> Answer {
+        Answer { value, trace: [value] }
+    }
+
+    #[cfg(not(feature = "slow"))]
+    pub fn new(value: u64) -> Answer {
+        Answer { value }
+    }
+}
+
+pub enum Mode {
+    Fast,
+    #[cfg(feature = "slow")]
+    Slow,
+}
+
+pub fn describe(mode: &Mode) -> &'static str {
+    match mode {
+        Mode::Fast => "fast",
+        #[cfg(feature = "slow")]
+        Mode::Slow => "slow",
+    }
+}
+
+pub fn is_loud() -> bool {
+    cfg!(feature = "loud")
+}
+
+#[cfg(feature = "loud")]
+pub fn greet() -> String {
+    helpers::greet()
+}
@@ -0,0 +1,9 @@
+use features::{describe, solve, Answer, Mode};
+
+fn main() {
+    let answer = Answer::new(solve(10));
+    println!("{:?} {} {}", answer, features::MODE, describe(&Mode::Fast));
+    println!("{} {}", features::platform(), features::is_loud());
+    #[cfg(feature = "loud")]
+    println!("{}", features::greet());
+}
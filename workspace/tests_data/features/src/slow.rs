@@ -0,0 +1,3 @@
+pub fn trace(value: u64) -> [u64; 1] {
+    [value]
+}
@@ -8,7 +8,7 @@ pub fn hello_world() {
 }
 
 }
-use lib_and_bin::hello_world::hello_world;
+use crate::lib_and_bin::hello_world::hello_world;
 
 fn main() {
     hello_world();
@@ -0,0 +1,30 @@
+pub mod macro_paths {
+pub mod util {
+pub const COUNT: usize = 2;
+
+pub fn base() -> u32 {
+    21
+}
+
+}
+
+pub fn values() -> Vec<u32> {
+    let values = vec![crate::macro_paths::util::base(); 2];
+    assert_eq!(values.len(), crate::macro_paths::util::COUNT);
+    assert!(crate::macro_paths::util::base() > 0, "{}", crate::macro_paths::util::base());
+    println!("{}", crate::macro_paths::util::base());
+    values
+}
+
+}
+pub mod solver {
+pub fn solve() -> String {
+    format!("{:?} {}", crate::macro_paths::values(), crate::macro_paths::util::COUNT)
+}
+
+}
+
+fn main() {
+    println!("{}", solver::solve());
+}
+
@@ -0,0 +1,9 @@
+pub mod util;
+
+pub fn values() -> Vec<u32> {
+    let values = vec![crate::util::base(); 2];
+    assert_eq!(values.len(), crate::util::COUNT);
+    assert!(crate::util::base() > 0, "{}", crate::util::base());
+    println!("{}", crate::util::base());
+    values
+}
@@ -0,0 +1,5 @@
+mod solver;
+
+fn main() {
+    println!("{}", solver::solve());
+}
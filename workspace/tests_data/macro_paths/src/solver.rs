@@ -0,0 +1,3 @@
+pub fn solve() -> String {
+    format!("{:?} {}", macro_paths::values(), macro_paths::util::COUNT)
+}
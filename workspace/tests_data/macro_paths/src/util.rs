@@ -0,0 +1,5 @@
+pub const COUNT: usize = 2;
+
+pub fn base() -> u32 {
+    21
+}
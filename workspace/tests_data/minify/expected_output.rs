@@ -0,0 +1,3 @@
+pub mod minify{pub mod text{pub fn banner()->&'static str{"/* not a comment */"}pub fn raw()->&'static str{r#"// kept "as is"
+
+  with its blank line"#}pub fn bytes()->usize{let pair=(1,(b'x',b"yz"));let letter='a' as u8;(pair.1).1.len()+pair.1.0 as usize-letter as usize}}pub fn divide<'a>(a:&'a i64,b:&'a i64)->i64{*a/ *b}pub fn range_sum(n:u64)->u64{(0..n).sum::<u64>()+(1..=n).count()as u64-n}pub const ANSWER:i64=42;}use crate::minify::{divide,range_sum};use crate::minify::text;macro_rules!square{($value:expr)=>{$value*$value};}fn main(){println!("{}",divide(&-12,&4));println!("{}",range_sum(4));println!("{}",square!(3)--1);println!("{}",text::banner());println!("{}",text::raw());println!("{}",text::bytes());println!("{}",crate::minify::ANSWER);}
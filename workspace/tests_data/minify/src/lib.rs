@@ -0,0 +1,27 @@
+//! Helpers used by the solution
+//!
+//! ```
+//! assert_eq!(minify::text::banner(), "/* not a comment */");
+//! ```
+
+pub mod text;
+
+/// Divide by a referenced value
+///
+/// # Examples
+/// ```
+/// assert_eq!(minify::divide(&6, &3), 2);
+/// ```
+pub fn divide<'a>(a: &'a i64, b: &'a i64) -> i64 {
+    // The space between `/` and `*` matters
+    *a / *b
+}
+
+#[doc = "Sum of a range"]
+pub fn range_sum(n: u64) -> u64 {
+    (0..n).sum::<u64>() + (1..=n).count() as u64 - n
+}
+
+/* A block comment
+   spanning several lines */
+pub const ANSWER: i64 = 42;
@@ -0,0 +1,19 @@
+use minify::{divide, range_sum};
+use minify::text;
+
+macro_rules! square {
+    ($value:expr) => {
+        $value * $value
+    };
+}
+
+fn main() {
+    // Print everything
+    println!("{}", divide(&-12, &4));
+    println!("{}", range_sum(4));
+    println!("{}", square!(3) - -1);
+    println!("{}", text::banner());
+    println!("{}", text::raw());
+    println!("{}", text::bytes());
+    println!("{}", minify::ANSWER);
+}
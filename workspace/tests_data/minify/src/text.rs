@@ -0,0 +1,15 @@
+pub fn banner() -> &'static str {
+    "/* not a comment */"
+}
+
+pub fn raw() -> &'static str {
+    r#"// kept "as is"
+
+  with its blank line"#
+}
+
+pub fn bytes() -> usize {
+    let pair = (1, (b'x', b"yz"));
+    let letter = 'a' as u8;
+    (pair.1).1.len() + pair.1.0 as usize - letter as usize
+}
@@ -0,0 +1,6 @@
+#[path = "generated/missing.rs"]
+mod missing;
+
+fn main() {
+    missing::run();
+}
@@ -0,0 +1,32 @@
+pub mod solver {
+pub fn solve(answer: u32) {
+    println!("{}", answer);
+}
+
+}
+
+mod inline {
+    pub mod nested {
+pub fn answer() -> u32 {
+    42
+}
+
+}
+}
+
+macro_rules! declare_module {
+    () => {
+        mod generated;
+    };
+}
+
+use crate::{
+    inline::nested::answer,
+    solver::solve,
+};
+
+fn main() {
+    // mod commented;
+    solve(answer());
+}
+
@@ -0,0 +1,22 @@
+#[cfg(not(test))]
+mod solver;
+
+mod inline {
+    pub mod nested;
+}
+
+macro_rules! declare_module {
+    () => {
+        mod generated;
+    };
+}
+
+use crate::{
+    inline::nested::answer,
+    solver::solve,
+};
+
+fn main() {
+    // mod commented;
+    solve(answer());
+}
@@ -0,0 +1,3 @@
+pub fn solve(answer: u32) {
+    println!("{}", answer);
+}
@@ -0,0 +1,33 @@
+pub mod legacy {
+pub mod child {
+pub const NAME: &str = "legacy/child.rs";
+
+}
+pub mod deep {
+pub mod leaf {
+pub const NAME: &str = "legacy/deep/leaf.rs";
+
+}
+
+}
+
+}
+pub mod modern {
+pub mod child {
+pub const NAME: &str = "modern/child.rs";
+
+}
+pub mod old_style {
+pub const NAME: &str = "modern/old_style/mod.rs";
+
+}
+
+}
+
+fn main() {
+    println!("{}", legacy::child::NAME);
+    println!("{}", legacy::deep::leaf::NAME);
+    println!("{}", modern::child::NAME);
+    println!("{}", modern::old_style::NAME);
+}
+
@@ -0,0 +1,2 @@
+pub mod child;
+pub mod deep;
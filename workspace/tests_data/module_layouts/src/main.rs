@@ -0,0 +1,9 @@
+mod legacy;
+mod modern;
+
+fn main() {
+    println!("{}", legacy::child::NAME);
+    println!("{}", legacy::deep::leaf::NAME);
+    println!("{}", modern::child::NAME);
+    println!("{}", modern::old_style::NAME);
+}
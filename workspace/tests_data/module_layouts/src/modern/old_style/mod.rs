@@ -0,0 +1 @@
+pub const NAME: &str = "modern/old_style/mod.rs";
@@ -0,0 +1,7 @@
+use multi_bins::numbers::double;
+use simple_lib::call::call;
+
+fn main() {
+    call();
+    println!("{}", double(3));
+}
@@ -0,0 +1,36 @@
+pub mod simple_lib {
+pub mod hello_world {
+
+pub fn hello_world() {
+    println!("Hello, world!");
+    eprintln!("yeah");
+}
+
+}
+pub mod call {
+use crate::simple_lib::hello_world::hello_world;
+
+pub fn call() {
+    hello_world();
+}
+
+}
+
+}
+pub mod multi_bins {
+pub mod numbers {
+pub fn double(value: u32) -> u32 {
+    value * 2
+}
+
+}
+
+}
+use crate::multi_bins::numbers::double;
+use crate::simple_lib::call::call;
+
+fn main() {
+    call();
+    println!("{}", double(3));
+}
+
@@ -0,0 +1,15 @@
+pub mod multi_bins {
+pub mod numbers {
+pub fn double(value: u32) -> u32 {
+    value * 2
+}
+
+}
+
+}
+use crate::multi_bins::numbers::double;
+
+fn main() {
+    println!("{}", double(2));
+}
+
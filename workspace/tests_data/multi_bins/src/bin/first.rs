@@ -0,0 +1,5 @@
+use multi_bins::numbers::double;
+
+fn main() {
+    println!("{}", double(1));
+}
@@ -0,0 +1,47 @@
+pub mod tables {
+pub mod lookup {
+pub const ZERO: u32 = 0;
+
+}
+
+}
+pub mod solver {
+pub mod math {
+pub fn identity(value: u32) -> u32 {
+    value
+}
+
+}
+
+pub fn solve(value: u32) -> u32 {
+    math::identity(value) + crate::tables::lookup::ZERO
+}
+
+}
+
+mod inline {
+    pub mod helpers {
+pub fn double(value: u32) -> u32 {
+    value * 2
+}
+
+}
+}
+
+#[path = "vendor"]
+mod vendored {
+    pub mod parser {
+pub fn parse(input: &str) -> u32 {
+    input.parse().unwrap()
+}
+
+}
+}
+
+use crate::inline::helpers::double;
+use crate::solver::solve;
+
+fn main() {
+    println!("{}", double(solve(vendored::parser::parse("21"))));
+}
+
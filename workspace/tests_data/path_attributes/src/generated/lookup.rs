@@ -0,0 +1 @@
+pub const ZERO: u32 = 0;
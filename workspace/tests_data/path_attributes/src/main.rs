@@ -0,0 +1,20 @@
+#[path = "generated/tables.rs"]
+mod tables;
+mod solver;
+
+mod inline {
+    #[path = "helpers.rs"]
+    pub mod helpers;
+}
+
+#[path = "vendor"]
+mod vendored {
+    pub mod parser;
+}
+
+use crate::inline::helpers::double;
+use crate::solver::solve;
+
+fn main() {
+    println!("{}", double(solve(vendored::parser::parse("21"))));
+}
@@ -0,0 +1,6 @@
+#[path = "solver_math.rs"]
+mod math;
+
+pub fn solve(value: u32) -> u32 {
+    math::identity(value) + crate::tables::lookup::ZERO
+}
@@ -0,0 +1,3 @@
+pub fn identity(value: u32) -> u32 {
+    value
+}
@@ -0,0 +1,11 @@
+pub mod unix_only {
+pub fn platform() -> &'static str {
+    "unix"
+}
+
+}
+use crate::unix_only::platform;
+fn main() {
+    println!("{}", platform());
+}
+
@@ -0,0 +1,8 @@
+#[cfg(unix)]
+use unix_only::platform;
+#[cfg(windows)]
+use windows_only::platform;
+
+fn main() {
+    println!("{}", platform());
+}
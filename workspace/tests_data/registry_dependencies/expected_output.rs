@@ -0,0 +1,27 @@
+pub mod tiny_math {
+pub mod gcd {
+/// Greatest common divisor
+pub fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 {
+        a
+    } else {
+        crate::tiny_math::gcd::gcd(b, a % b)
+    }
+}
+
+}
+
+}
+pub mod tiny_text {
+pub fn shout(text: &str) -> String {
+    format!("{}!", text.to_uppercase())
+}
+
+}
+use crate::tiny_text::shout;
+use crate::tiny_math::gcd::gcd;
+
+fn main() {
+    println!("{}", shout(&gcd(12, 18).to_string()));
+}
+
@@ -0,0 +1,3 @@
+pub fn gcd(_a: u64, _b: u64) -> u64 {
+    unimplemented!()
+}
@@ -0,0 +1,8 @@
+/// Greatest common divisor
+pub fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 {
+        a
+    } else {
+        crate::gcd::gcd(b, a % b)
+    }
+}
@@ -0,0 +1,6 @@
+use text::shout;
+use tiny_math::gcd::gcd;
+
+fn main() {
+    println!("{}", shout(&gcd(12, 18).to_string()));
+}
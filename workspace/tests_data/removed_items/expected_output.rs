@@ -0,0 +1,13 @@
+mod shapes {
+    pub fn square(side: u32) -> u32 {
+        side * side
+    }
+}
+
+fn kept() -> u32 {
+    1
+}
+fn main() {
+    println!("{} {}", kept(), shapes::square(2));
+}
+
@@ -0,0 +1,31 @@
+mod shapes {
+    pub fn square(side: u32) -> u32 {
+        side * side
+    }
+
+    #[cfg(feature = "extra")]
+    pub fn cube(side: u32) -> u32 {
+        side * side * side
+    }
+
+    #[cfg(feature = "extra")]
+    pub fn tesseract(side: u32) -> u32 {
+        side * side * side * side
+    }
+}
+
+fn kept() -> u32 {
+    1
+}
+#[cfg(feature = "extra")]
+fn first() -> u32 {
+    2
+}
+
+#[cfg(feature = "extra")]
+fn second() -> u32 {
+    3
+}
+fn main() {
+    println!("{} {}", kept(), shapes::square(2));
+}
@@ -0,0 +1,18 @@
+use std::io::{self, BufRead, BufWriter, Write};
+
+fn main() {
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    let mut output = BufWriter::new(stdout.lock());
+    // Echo the input line by line, writing before everything is read
+    for line in stdin.lock().lines() {
+        let line = line.unwrap();
+        if line == "loop" {
+            output.flush().unwrap();
+            loop {
+                std::thread::yield_now();
+            }
+        }
+        writeln!(output, "{}", line).unwrap();
+    }
+}
@@ -0,0 +1,8 @@
+use std::io::{self, Read};
+
+/// Read all the whitespace separated numbers of the standard input
+pub fn read_numbers() -> Vec<i64> {
+    let mut input = String::new();
+    io::stdin().read_to_string(&mut input).unwrap();
+    input.split_whitespace().map(|number| number.parse().unwrap()).collect()
+}
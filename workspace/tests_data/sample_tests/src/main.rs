@@ -0,0 +1,7 @@
+use sample_tests::input::read_numbers;
+
+fn main() {
+    let numbers = read_numbers();
+    println!("{}", numbers.iter().sum::<i64>());
+    println!("{}", numbers.iter().max().unwrap_or(&0));
+}
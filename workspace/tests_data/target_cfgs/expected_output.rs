@@ -0,0 +1,24 @@
+fn platform() -> &'static str {
+    "linux"
+}
+
+const WORD_BITS: u32 = 64;
+
+fn read_input() -> &'static str {
+    "from the judge"
+}
+
+struct Stats {
+    checks: u32,
+}
+
+fn main() {
+    let mut stats = Stats { checks: 0 };
+    if false {
+        stats.checks += 1;
+    }
+    #[cfg(target_feature = "avx2")]
+    println!("vectorized");
+    println!("{} {} {} {}", platform(), WORD_BITS, read_input(), stats.checks);
+}
+
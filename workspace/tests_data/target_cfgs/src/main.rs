@@ -0,0 +1,45 @@
+#[cfg(target_os = "linux")]
+fn platform() -> &'static str {
+    "linux"
+}
+
+#[cfg(target_os = "windows")]
+fn platform() -> &'static str {
+    "windows"
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+fn platform() -> &'static str {
+    "other"
+}
+
+#[cfg(all(unix, target_pointer_width = "64"))]
+const WORD_BITS: u32 = 64;
+
+#[cfg(not(all(unix, target_pointer_width = "64")))]
+const WORD_BITS: u32 = 32;
+
+#[cfg(judge)]
+fn read_input() -> &'static str {
+    "from the judge"
+}
+
+#[cfg(not(judge))]
+fn read_input() -> &'static str {
+    "from a local file"
+}
+
+#[cfg_attr(debug_assertions, derive(Debug))]
+struct Stats {
+    checks: u32,
+}
+
+fn main() {
+    let mut stats = Stats { checks: 0 };
+    if cfg!(debug_assertions) {
+        stats.checks += 1;
+    }
+    #[cfg(all(target_feature = "avx2", not(windows)))]
+    println!("vectorized");
+    println!("{} {} {} {}", platform(), WORD_BITS, read_input(), stats.checks);
+}
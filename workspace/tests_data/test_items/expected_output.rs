@@ -0,0 +1,27 @@
+pub mod test_items {
+pub mod geometry {
+pub struct Point {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl Point {
+    pub fn norm(&self) -> i32 {
+        self.x.abs() + self.y.abs()
+    }
+}
+
+}
+
+pub fn double(value: u32) -> u32 {
+    value * 2
+}
+
+}
+use crate::test_items::double;
+use crate::test_items::geometry::Point;
+
+fn main() {
+    println!("{} {}", double(21), Point { x: 1, y: -2 }.norm());
+}
+
@@ -0,0 +1,63 @@
+pub mod test_items {
+pub mod geometry {
+pub struct Point {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl Point {
+    pub fn norm(&self) -> i32 {
+        self.x.abs() + self.y.abs()
+    }
+
+    #[cfg(test)]
+    fn origin() -> Point {
+        Point { x: 0, y: 0 }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+use super::Point;
+use quickcheck::quickcheck;
+
+#[test]
+fn origin_norm() {
+    assert_eq!(Point::origin().norm(), 0);
+}
+
+}
+
+}
+
+pub fn double(value: u32) -> u32 {
+    value * 2
+}
+
+#[cfg(test)]
+use std::collections::HashMap;
+
+#[test]
+fn double_works() {
+    assert_eq!(double(2), 4);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn double_zero() {
+        assert_eq!(double(0), 0);
+        assert!(HashMap::<u32, u32>::new().is_empty());
+    }
+}
+
+}
+use crate::test_items::double;
+use crate::test_items::geometry::Point;
+
+fn main() {
+    println!("{} {}", double(21), Point { x: 1, y: -2 }.norm());
+}
+
@@ -0,0 +1,18 @@
+pub struct Point {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl Point {
+    pub fn norm(&self) -> i32 {
+        self.x.abs() + self.y.abs()
+    }
+
+    #[cfg(test)]
+    fn origin() -> Point {
+        Point { x: 0, y: 0 }
+    }
+}
+
+#[cfg(test)]
+mod tests;
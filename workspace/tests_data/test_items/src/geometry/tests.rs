@@ -0,0 +1,7 @@
+use super::Point;
+use quickcheck::quickcheck;
+
+#[test]
+fn origin_norm() {
+    assert_eq!(Point::origin().norm(), 0);
+}
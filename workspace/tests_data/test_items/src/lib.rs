@@ -0,0 +1,24 @@
+pub mod geometry;
+
+pub fn double(value: u32) -> u32 {
+    value * 2
+}
+
+#[cfg(test)]
+use std::collections::HashMap;
+
+#[test]
+fn double_works() {
+    assert_eq!(double(2), 4);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn double_zero() {
+        assert_eq!(double(0), 0);
+        assert!(HashMap::<u32, u32>::new().is_empty());
+    }
+}
@@ -0,0 +1,6 @@
+use test_items::double;
+use test_items::geometry::Point;
+
+fn main() {
+    println!("{} {}", double(21), Point { x: 1, y: -2 }.norm());
+}
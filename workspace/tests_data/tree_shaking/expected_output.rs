@@ -0,0 +1,63 @@
+pub mod tree_shaking {
+pub mod math {
+use std::ops::Add;
+
+/// Greatest common divisor
+pub fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+pub fn lcm(a: u64, b: u64) -> u64 {
+    a / gcd(a, b) * b
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Point {
+    pub x: i64,
+    pub y: i64,
+}
+
+impl Point {
+    pub fn new(x: i64, y: i64) -> Point {
+        Point { x, y }
+    }
+
+    pub fn manhattan(&self) -> i64 {
+        self.x.abs() + self.y.abs()
+    }
+}
+
+impl Add for Point {
+    type Output = Point;
+
+    fn add(self, other: Point) -> Point {
+        Point::new(self.x + other.x, self.y + other.y)
+    }
+}
+
+pub trait Squared {
+    fn squared(&self) -> Self;
+}
+
+impl Squared for u64 {
+    fn squared(&self) -> u64 {
+        self * self
+    }
+}
+
+}
+/// Largest value read from the input
+pub const MAX_VALUE: u64 = 1_000_000_007;
+}
+use crate::tree_shaking::math::{lcm, Point, Squared};
+use crate::tree_shaking::MAX_VALUE;
+use std::io::Write;
+
+fn main() {
+    let point = Point::new(1, 2) + Point::new(3, 4);
+    let value = lcm(4, 6).squared();
+    let mut output = std::io::stdout();
+    writeln!(output, "{:?} {}", point, point.manhattan()).unwrap();
+    writeln!(output, "{value} {MAX_VALUE}").unwrap();
+}
+
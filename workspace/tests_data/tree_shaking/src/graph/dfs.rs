@@ -0,0 +1,8 @@
+pub fn dfs(adjacency: &[Vec<usize>], start: usize, visited: &mut Vec<bool>) {
+    visited[start] = true;
+    for &next in &adjacency[start] {
+        if !visited[next] {
+            dfs(adjacency, next, visited);
+        }
+    }
+}
@@ -0,0 +1,3 @@
+mod dfs;
+
+pub use self::dfs::dfs;
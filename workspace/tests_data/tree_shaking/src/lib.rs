@@ -0,0 +1,7 @@
+pub mod graph;
+pub mod math;
+pub mod strings;
+
+/// Largest value read from the input
+pub const MAX_VALUE: u64 = 1_000_000_007;
+pub const UNUSED_LIMIT: usize = 42;
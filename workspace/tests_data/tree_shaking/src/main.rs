@@ -0,0 +1,11 @@
+use tree_shaking::math::{lcm, Point, Squared};
+use tree_shaking::MAX_VALUE;
+use std::io::Write;
+
+fn main() {
+    let point = Point::new(1, 2) + Point::new(3, 4);
+    let value = lcm(4, 6).squared();
+    let mut output = std::io::stdout();
+    writeln!(output, "{:?} {}", point, point.manhattan()).unwrap();
+    writeln!(output, "{value} {MAX_VALUE}").unwrap();
+}
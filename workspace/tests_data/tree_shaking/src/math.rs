@@ -0,0 +1,76 @@
+use std::ops::Add;
+
+/// Greatest common divisor
+pub fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+pub fn lcm(a: u64, b: u64) -> u64 {
+    a / gcd(a, b) * b
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Point {
+    pub x: i64,
+    pub y: i64,
+}
+
+impl Point {
+    pub fn new(x: i64, y: i64) -> Point {
+        Point { x, y }
+    }
+
+    pub fn manhattan(&self) -> i64 {
+        self.x.abs() + self.y.abs()
+    }
+
+    pub fn rotate(&self) -> Point {
+        Point::new(-self.y, self.x)
+    }
+
+    pub fn scale(&self, factor: i64) -> Point {
+        Point::new(self.x * factor, self.y * factor)
+    }
+}
+
+impl Add for Point {
+    type Output = Point;
+
+    fn add(self, other: Point) -> Point {
+        Point::new(self.x + other.x, self.y + other.y)
+    }
+}
+
+pub trait Squared {
+    fn squared(&self) -> Self;
+}
+
+impl Squared for u64 {
+    fn squared(&self) -> u64 {
+        self * self
+    }
+}
+
+pub trait Halved {
+    fn halved(&self) -> Self;
+}
+
+impl Halved for u64 {
+    fn halved(&self) -> u64 {
+        self / 2
+    }
+}
+
+pub struct Matrix {
+    pub cells: Vec<Vec<i64>>,
+}
+
+impl Matrix {
+    pub fn identity(size: usize) -> Matrix {
+        let mut cells = vec![vec![0; size]; size];
+        for i in 0..size {
+            cells[i][i] = 1;
+        }
+        Matrix { cells }
+    }
+}
@@ -0,0 +1,9 @@
+use crate::math::gcd;
+
+pub fn reverse(text: &str) -> String {
+    text.chars().rev().collect()
+}
+
+pub fn common_period(a: &str, b: &str) -> u64 {
+    gcd(a.len() as u64, b.len() as u64)
+}
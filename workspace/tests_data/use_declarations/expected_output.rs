@@ -0,0 +1,70 @@
+pub mod simple_lib {
+pub mod hello_world {
+
+pub fn hello_world() {
+    println!("Hello, world!");
+    eprintln!("yeah");
+}
+
+}
+pub mod call {
+use crate::simple_lib::hello_world::hello_world;
+
+pub fn call() {
+    hello_world();
+}
+
+}
+
+}
+pub mod use_declarations {
+pub mod geometry {
+pub mod point {
+#[derive(Debug, Clone, Copy)]
+pub struct Point {
+    pub x: i64,
+    pub y: i64,
+}
+
+pub fn origin() -> Point {
+    Point { x: 0, y: 0 }
+}
+
+}
+
+pub use self::point::Point;
+
+}
+pub mod solver {
+use crate::use_declarations::geometry::point::*;
+use crate::use_declarations::{
+    geometry::{
+        self,
+        point::{origin as zero, Point as P},
+    },
+    solver,
+};
+use crate::simple_lib::call::call;
+use {crate::simple_lib::call as call_module, std::fmt::Debug};
+
+pub fn solve() -> P {
+    call();
+    call_module::call();
+    let point: geometry::Point = zero();
+    solver::describe(&point);
+    origin()
+}
+
+fn describe<T: Debug>(value: &T) {
+    println!("{:?}", value);
+}
+
+}
+
+}
+use crate::use_declarations::solver::solve;
+
+fn main() {
+    println!("{:?}", solve());
+}
+
@@ -0,0 +1,3 @@
+pub mod point;
+
+pub use self::point::Point;
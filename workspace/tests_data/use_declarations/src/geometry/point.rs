@@ -0,0 +1,9 @@
+#[derive(Debug, Clone, Copy)]
+pub struct Point {
+    pub x: i64,
+    pub y: i64,
+}
+
+pub fn origin() -> Point {
+    Point { x: 0, y: 0 }
+}
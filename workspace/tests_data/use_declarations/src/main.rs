@@ -0,0 +1,5 @@
+use use_declarations::solver::solve;
+
+fn main() {
+    println!("{:?}", solve());
+}
@@ -0,0 +1,22 @@
+use crate::geometry::point::*;
+use crate::{
+    geometry::{
+        self,
+        point::{origin as zero, Point as P},
+    },
+    solver,
+};
+use simple_lib::call::call;
+use {simple_lib::call as call_module, std::fmt::Debug};
+
+pub fn solve() -> P {
+    call();
+    call_module::call();
+    let point: geometry::Point = zero();
+    solver::describe(&point);
+    origin()
+}
+
+fn describe<T: Debug>(value: &T) {
+    println!("{:?}", value);
+}